@@ -0,0 +1,57 @@
+//  ================================================================
+//
+//    A* search benchmarks
+//
+//  Dijkstra-like search (zero estimate) across a square grid,
+//  so every node is visited before reaching the far corner.
+//
+//  ================================================================
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use meritrank_service::astar::*;
+use meritrank_service::log::*;
+
+fn grid_neighbor(size : i64, id : i64, index : usize) -> Option<Link<i64, i64>> {
+  let x = id % size;
+  let y = id / size;
+
+  let candidates = [
+    (x + 1, y),
+    (x, y + 1),
+    (x - 1, y),
+    (x, y - 1),
+  ];
+
+  candidates
+    .iter()
+    .filter(|(nx, ny)| *nx >= 0 && *ny >= 0 && *nx < size && *ny < size)
+    .nth(index)
+    .map(|(nx, ny)| Link::<i64, i64> {
+      neighbor       : ny * size + nx,
+      exact_distance : 1 + (nx * 7 + ny * 13) % 5,
+      estimate       : 0,
+    })
+}
+
+fn grid_search(c : &mut Criterion) {
+  set_subsystem_log_level(SUBSYSTEM_ASTAR, LEVEL_ERROR);
+
+  let mut group = c.benchmark_group("astar_grid");
+  group.sample_size(10);
+
+  for size in [16i64, 32, 64, 128] {
+    group.bench_with_input(BenchmarkId::from_parameter(size * size), &size, |b, &size| {
+      let mut get_neighbor = |id : i64, index : usize| grid_neighbor(size, id, index);
+
+      b.iter(|| {
+        let res = shortest_path(0i64, size * size - 1, i64::MAX, usize::MAX, &[], &[], &mut get_neighbor);
+        assert!(res.is_ok());
+      });
+    });
+  }
+
+  group.finish();
+}
+
+criterion_group!(benches, grid_search);
+criterion_main!(benches);
@@ -13,7 +13,16 @@
 
 #[allow(non_camel_case_types)]
 mod astar_internal {
-  use std::{ops::Add, fmt::Debug};
+  use std::{
+    ops::Add,
+    fmt::Debug,
+    hash::Hash,
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+  };
+
+  use crate::log_trace;
+  use crate::log::*;
 
   #[derive(Debug, Clone, PartialEq, Default)]
   pub struct Neighbor_Request<Node_Id> {
@@ -52,156 +61,215 @@ mod astar_internal {
     ENUM_NEIGHBORS,
   }
 
+  //  Entry of the open set priority queue.
+  //
+  //  The queue may contain outdated entries for nodes that were
+  //  replaced with a better distance; those are skipped when popped.
+  //
+  #[derive(Debug, Clone)]
+  pub struct Open_Entry<Node_Id, Cost> {
+    pub total    : Cost,
+    pub sequence : usize,
+    pub node     : Node<Node_Id, Cost>,
+  }
+
+  impl<Node_Id, Cost : PartialOrd> PartialEq for Open_Entry<Node_Id, Cost> {
+    fn eq(&self, other : &Self) -> bool {
+      self.cmp(other) == Ordering::Equal
+    }
+  }
+
+  impl<Node_Id, Cost : PartialOrd> Eq for Open_Entry<Node_Id, Cost> {}
+
+  impl<Node_Id, Cost : PartialOrd> PartialOrd for Open_Entry<Node_Id, Cost> {
+    fn partial_cmp(&self, other : &Self) -> Option<Ordering> {
+      Some(self.cmp(other))
+    }
+  }
+
+  impl<Node_Id, Cost : PartialOrd> Ord for Open_Entry<Node_Id, Cost> {
+    fn cmp(&self, other : &Self) -> Ordering {
+      //  Reversed, so the max-heap pops the nearest node first.
+      //  Ties are resolved in insertion order.
+      //
+      other.total
+        .partial_cmp(&self.total)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+  }
+
   #[derive(Clone)]
   pub struct State<Node_Id, Cost> {
     pub stage            : Stage,
-    pub num_open         : usize,
-    pub num_closed       : usize,
+    pub open             : BinaryHeap<Open_Entry<Node_Id, Cost>>,
+    pub open_distance    : HashMap<Node_Id, Cost>,
+    pub closed           : HashMap<Node_Id, Node<Node_Id, Cost>>,
+    pub sequence         : usize,
+    pub max_nodes        : usize,
     pub source           : Node_Id,
     pub destination      : Node_Id,
-    pub closest_index    : usize,
+    pub closest          : Option<Node_Id>,
     pub closest_estimate : Cost,
     pub node             : Option<Node<Node_Id, Cost>>,
     pub neighbor_index   : usize,
   }
 
+  //  `max_nodes` limits the total number of nodes in the open and
+  //  closed sets. When the limit is reached, the iteration returns
+  //  `OUT_OF_MEMORY`; the caller may increase `state.max_nodes`
+  //  and continue the search.
+  //
   pub fn init<Node_Id, Cost>(
-    open        : &mut [Node<Node_Id, Cost>],
     source      : Node_Id,
     destination : Node_Id,
-    max_cost    : Cost
+    max_cost    : Cost,
+    max_nodes   : usize
   ) -> State<Node_Id, Cost>
     where
-      Node_Id : Clone,
-      Cost    : Clone + Default
+      Node_Id : Clone + Hash + Eq,
+      Cost    : Clone + Default + PartialOrd + Add<Output = Cost>
   {
-    if open.len() != 0 {
-      open[0] = Node::<Node_Id, Cost> {
-        id             : source.clone(),
-        previous       : None,
-        exact_distance : Cost::default(),
-        estimate       : max_cost.clone(),
-        count          : 1,
-      };
-    }
-
-    return State {
-      stage      : Stage::SEARCH_NEAREST,
-      num_open   : if open.len() != 0 { 1 } else { 0 },
-      num_closed : 0,
-      source,
+    let mut state = State {
+      stage            : Stage::SEARCH_NEAREST,
+      open             : BinaryHeap::new(),
+      open_distance    : HashMap::new(),
+      closed           : HashMap::new(),
+      sequence         : 0,
+      max_nodes,
+      source           : source.clone(),
       destination,
-      closest_index    : 0,
-      closest_estimate : max_cost,
+      closest          : Some(source.clone()),
+      closest_estimate : max_cost.clone(),
       node             : None,
       neighbor_index   : 0,
     };
+
+    open_push(&mut state, Node::<Node_Id, Cost> {
+      id             : source,
+      previous       : None,
+      exact_distance : Cost::default(),
+      estimate       : max_cost,
+      count          : 1,
+    });
+
+    return state;
   }
 
-  pub fn path<Node_Id, Cost>(
-    closed   : &[Node<Node_Id, Cost>],
-    state    : &State<Node_Id, Cost>,
-    node_ids : &mut [Node_Id]
-  ) -> usize
+  fn open_push<Node_Id, Cost>(
+    state : &mut State<Node_Id, Cost>,
+    node  : Node<Node_Id, Cost>
+  )
     where
-      Node_Id : Clone + PartialEq
+      Node_Id : Clone + Hash + Eq,
+      Cost    : Clone + PartialOrd + Add<Output = Cost>
   {
-    if closed.is_empty()                       ||
-       state.closest_index >= state.num_closed ||
-       node_ids.len() < state.num_closed {
-      return 0;
+    state.open_distance.insert(node.id.clone(), node.exact_distance.clone());
+    state.open.push(Open_Entry {
+      total    : node.exact_distance.clone() + node.estimate.clone(),
+      sequence : state.sequence,
+      node,
+    });
+    state.sequence += 1;
+  }
+
+  fn open_pop<Node_Id, Cost>(
+    state : &mut State<Node_Id, Cost>
+  ) -> Option<Node<Node_Id, Cost>>
+    where
+      Node_Id : Clone + Hash + Eq,
+      Cost    : Clone + PartialOrd
+  {
+    while let Some(entry) = state.open.pop() {
+      //  Skip outdated entries
+      //
+      let actual = match state.open_distance.get(&entry.node.id) {
+        Some(distance) => !(entry.node.exact_distance > *distance),
+        None           => false,
+      };
+
+      if actual {
+        state.open_distance.remove(&entry.node.id);
+        return Some(entry.node);
+      }
     }
 
-    let mut current = state.closest_index;
+    return None;
+  }
 
-    let mut num_nodes : usize = 1;
-    node_ids[0] = closed[current].id.clone();
+  pub fn path<Node_Id, Cost>(
+    state : &State<Node_Id, Cost>
+  ) -> Vec<Node_Id>
+    where
+      Node_Id : Clone + Hash + Eq
+  {
+    let mut node_ids : Vec<Node_Id> = vec![];
 
-    loop {
-      if node_ids[num_nodes - 1] == state.source {
-        break;
-      }
+    let mut current = match &state.closest {
+      Some(id) => match state.closed.get(id) {
+        Some(node) => node,
+        None       => return vec![],
+      },
+      None => return vec![],
+    };
 
-      if num_nodes >= state.num_closed {
-        return 0;
-      }
+    loop {
+      node_ids.push(current.id.clone());
 
-      let mut index = usize::MAX;
-      for i in 0..state.num_closed {
-        if Some(closed[i].id.clone()) == closed[current].previous {
-          index = i;
-          break;
-        }
+      if current.id == state.source {
+        break;
       }
 
-      if index == usize::MAX {
-        return 0;
+      if node_ids.len() > state.closed.len() {
+        return vec![];
       }
 
-      node_ids[num_nodes] = closed[index].id.clone();
-      num_nodes += 1;
-      current = index;
+      current = match &current.previous {
+        Some(id) => match state.closed.get(id) {
+          Some(node) => node,
+          None       => return vec![],
+        },
+        None => return vec![],
+      };
     }
 
-    for i in 0..num_nodes/2 {
-      let x                       = node_ids[i].clone();
-      node_ids[i]                 = node_ids[num_nodes - 1 - i].clone();
-      node_ids[num_nodes - 1 - i] = x;
-    }
+    node_ids.reverse();
 
-    return num_nodes;
+    return node_ids;
   }
 
   pub fn iteration<Node_Id, Cost>(
-    open     : &mut [Node<Node_Id, Cost>],
-    closed   : &mut [Node<Node_Id, Cost>],
     state    : &mut State<Node_Id, Cost>,
     neighbor : Option<Link<Node_Id, Cost>>,
   ) -> Status<Node_Id>
     where
-      Node_Id : Debug + Clone + Default + PartialEq,
+      Node_Id : Debug + Clone + Hash + Eq,
       Cost    : Debug + Clone + Default + PartialOrd + Add<Output = Cost>,
   {
     match state.stage {
       Stage::SEARCH_NEAREST => {
-        if state.num_open == 0 {
-          return Status::FAIL;
-        }
-
         //  Check if we need more memory
         //
-        if state.num_open   + 1 >= open  .len() ||
-           state.num_closed + 2 >= closed.len() {
+        if state.open_distance.len() + state.closed.len() + 2 > state.max_nodes {
           return Status::OUT_OF_MEMORY;
         }
 
-        //  Find the nearest node to the destination in the open set
+        //  Take the nearest node to the destination from the open set
         //
 
-        let mut index_in_open : usize = 0;
-        for index in 1..state.num_open {
-          let node    = open[index].clone();
-          let nearest = open[index_in_open].clone();
-          if node   .exact_distance + node   .estimate <
-             nearest.exact_distance + nearest.estimate {
-            index_in_open = index;
-          }
-        }
+        let nearest_node = match open_pop(state) {
+          Some(x) => x,
+          None    => return Status::FAIL,
+        };
 
-        let nearest_node = open[index_in_open].clone();
         state.node = Some(nearest_node.clone());
-        if index_in_open != state.num_open - 1 {
-          open[index_in_open] = open[state.num_open - 1].clone();
-        }
-        state.num_open -= 1;
 
         //  Check if we reached the destination
         //
         if nearest_node.id == state.destination {
-          state.closest_index      = state.num_closed;
-          state.closest_estimate   = Cost::default();
-          closed[state.num_closed] = nearest_node;
-          state.num_closed += 1;
+          state.closest          = Some(nearest_node.id.clone());
+          state.closest_estimate = Cost::default();
+          state.closed.insert(nearest_node.id.clone(), nearest_node);
 
           //  Finish the search
           return Status::SUCCESS;
@@ -225,19 +293,19 @@ mod astar_internal {
           Some(link) => {
             //  Check if we need more memory
             //
-            if state.num_open + 1 >= open.len() {
+            if state.open_distance.len() + state.closed.len() + 2 > state.max_nodes {
               return Status::OUT_OF_MEMORY;
             }
 
             //  Calculate distance estimations
             //
 
-            let exact_distance = nearest_node.clone().exact_distance + link.clone().exact_distance;
-            let estimate       = link.clone().estimate;
+            let exact_distance = nearest_node.exact_distance.clone() + link.exact_distance;
+            let estimate       = link.estimate;
 
             let neighbor_node = Node {
               id             : link.neighbor,
-              previous       : Some(nearest_node.clone().id),
+              previous       : Some(nearest_node.id.clone()),
               exact_distance,
               estimate,
               count          : nearest_node.count + 1,
@@ -246,12 +314,11 @@ mod astar_internal {
             //  Check if we reached the destination
             //
             if neighbor_node.id == state.destination {
-              state.closest_index          = state.num_closed + 1;
-              state.closest_estimate       = Cost::default();
-              closed[state.num_closed]     = nearest_node;
-              closed[state.num_closed + 1] = neighbor_node.clone();
-              state.node        = None;
-              state.num_closed += 2;
+              state.closest          = Some(neighbor_node.id.clone());
+              state.closest_estimate = Cost::default();
+              state.closed.insert(nearest_node.id.clone(), nearest_node);
+              state.closed.insert(neighbor_node.id.clone(), neighbor_node);
+              state.node = None;
 
               //  Finish the search
               return Status::SUCCESS;
@@ -260,24 +327,16 @@ mod astar_internal {
             //  Check if this node is already in the closed set
             //
 
-            let mut index_in_closed = usize::MAX;
-            for i in 0..state.num_closed {
-              if closed[i].id == neighbor_node.id {
-                index_in_closed = i;
-                break;
-              }
-            }
-
-            if index_in_closed != usize::MAX {
+            if let Some(closed_node) = state.closed.get_mut(&neighbor_node.id) {
               //  Check if this node has a better distance
-              if neighbor_node.exact_distance < closed[index_in_closed].exact_distance {
+              if neighbor_node.exact_distance < closed_node.exact_distance {
                 if neighbor_node.estimate < state.closest_estimate {
-                  state.closest_index    = index_in_closed;
-                  state.closest_estimate = neighbor_node.clone().estimate;
+                  state.closest          = Some(neighbor_node.id.clone());
+                  state.closest_estimate = neighbor_node.estimate.clone();
                 }
 
                 //  Replace the node
-                closed[index_in_closed] = neighbor_node;
+                *closed_node = neighbor_node;
               }
 
               //  Skip this node and proceed to the next neighbor node
@@ -294,19 +353,11 @@ mod astar_internal {
             //  Check if this node is already in the open set
             //
 
-            let mut index_in_open : usize = usize::MAX;
-            for i in 0..state.num_open {
-              if open[i].id == neighbor_node.id {
-                index_in_open = i;
-                break;
-              }
-            }
-
-            if index_in_open != usize::MAX {
+            if let Some(open_distance) = state.open_distance.get(&neighbor_node.id) {
               //  Check if this node has a better distance
-              if neighbor_node.exact_distance < open[index_in_open].exact_distance {
+              if neighbor_node.exact_distance < *open_distance {
                 //  Replace the node
-                open[index_in_open] = neighbor_node;
+                open_push(state, neighbor_node);
               }
 
               //  Skip this node and proceed to the next neighbor node
@@ -320,8 +371,7 @@ mod astar_internal {
               });
             }
 
-            open[state.num_open] = neighbor_node;
-            state.num_open += 1;
+            open_push(state, neighbor_node);
 
             //  Proceed to the next neighbor node
             //
@@ -336,13 +386,12 @@ mod astar_internal {
 
           None => {
             if nearest_node.estimate < state.closest_estimate {
-              state.closest_index    = state.num_closed;
-              state.closest_estimate = nearest_node.clone().estimate;
+              state.closest          = Some(nearest_node.id.clone());
+              state.closest_estimate = nearest_node.estimate.clone();
             }
 
-            closed[state.num_closed] = nearest_node;
-            state.node               = None;
-            state.num_closed        += 1;
+            state.closed.insert(nearest_node.id.clone(), nearest_node);
+            state.node = None;
 
             //  Proceed to the nearest node search
             //
@@ -355,6 +404,239 @@ mod astar_internal {
       },
     };
   }
+
+  //  Run the search until it succeeds, fails or runs out of steps.
+  //  Pass `usize::MAX` as `max_steps` for an unlimited search.
+  //  Nodes and edges from the exclusion lists are skipped.
+  //
+  //  Returns the path with its cost, or the last status on failure.
+  //
+  pub fn shortest_path<Node_Id, Cost, Neighbor>(
+    source         : Node_Id,
+    destination    : Node_Id,
+    max_cost       : Cost,
+    max_steps      : usize,
+    excluded_nodes : &[Node_Id],
+    excluded_edges : &[(Node_Id, Node_Id)],
+    get_neighbor   : &mut Neighbor,
+  ) -> Result<(Vec<Node_Id>, Cost), Status<Node_Id>>
+    where
+      Node_Id  : Debug + Clone + Hash + Eq,
+      Cost     : Debug + Clone + Default + PartialOrd + Add<Output = Cost>,
+      Neighbor : FnMut(Node_Id, usize) -> Option<Link<Node_Id, Cost>>,
+  {
+    log_trace!("shortest_path: {:?} -> {:?}, {} steps, {} excluded nodes, {} excluded edges",
+               source, destination, max_steps, excluded_nodes.len(), excluded_edges.len());
+
+    let mut state = init(source, destination, max_cost, usize::MAX);
+
+    let mut neighbor  = None;
+    let mut status    = Status::PROGRESS;
+    let mut raw_index = 0;
+    let mut steps     = 0;
+
+    for step in 0..max_steps {
+      steps  = step + 1;
+      status = iteration(&mut state, neighbor.clone());
+
+      match status.clone() {
+        Status::NEIGHBOR(request) => {
+          //  Neighbors are always requested sequentially,
+          //  so we keep our own index to skip excluded ones.
+          //
+          if request.index == 0 {
+            raw_index = 0;
+          }
+
+          neighbor = loop {
+            match get_neighbor(request.node.clone(), raw_index) {
+              None       => break None,
+              Some(link) => {
+                raw_index += 1;
+
+                if excluded_nodes.contains(&link.neighbor) ||
+                   excluded_edges.contains(&(request.node.clone(), link.neighbor.clone())) {
+                  continue;
+                }
+
+                break Some(link);
+              },
+            }
+          };
+        },
+        Status::OUT_OF_MEMORY => break,
+        Status::SUCCESS       => break,
+        Status::FAIL          => break,
+        Status::PROGRESS      => {},
+      };
+    }
+
+    if status != Status::SUCCESS {
+      if steps == max_steps && status != Status::FAIL && status != Status::OUT_OF_MEMORY {
+        log_trace!("shortest_path: step budget exhausted after {} steps, {} closed nodes", steps, state.closed.len());
+      } else {
+        log_trace!("shortest_path: {:?} after {} steps, {} closed nodes", status, steps, state.closed.len());
+      }
+      return Err(status);
+    }
+
+    let node_ids = path(&state);
+
+    let cost = match node_ids.last().and_then(|id| state.closed.get(id)) {
+      Some(node) => node.exact_distance.clone(),
+      None       => return Err(Status::FAIL),
+    };
+
+    log_trace!("shortest_path: found {} nodes with cost {:?} after {} steps, {} closed nodes",
+               node_ids.len(), cost, steps, state.closed.len());
+
+    return Ok((node_ids, cost));
+  }
+
+  //  Total cost of the path, or None if some edge does not exist.
+  //
+  pub fn path_cost<Node_Id, Cost, Neighbor>(
+    node_ids     : &[Node_Id],
+    get_neighbor : &mut Neighbor,
+  ) -> Option<Cost>
+    where
+      Node_Id  : Clone + PartialEq,
+      Cost     : Clone + Default + Add<Output = Cost>,
+      Neighbor : FnMut(Node_Id, usize) -> Option<Link<Node_Id, Cost>>,
+  {
+    let mut cost = Cost::default();
+
+    for k in 1..node_ids.len() {
+      let mut index = 0;
+
+      loop {
+        match get_neighbor(node_ids[k - 1].clone(), index) {
+          None       => return None,
+          Some(link) => {
+            if link.neighbor == node_ids[k] {
+              cost = cost + link.exact_distance;
+              break;
+            }
+          },
+        };
+
+        index += 1;
+      }
+    }
+
+    return Some(cost);
+  }
+
+  //  Yen's algorithm for K shortest loopless paths.
+  //
+  //  Paths are sorted by cost. Paths longer than `max_length` edges
+  //  are dropped from the result and don't count towards `num_paths`,
+  //  but are still used as roots for alternative paths.
+  //
+  pub fn k_shortest_paths<Node_Id, Cost, Neighbor>(
+    source       : Node_Id,
+    destination  : Node_Id,
+    max_cost     : Cost,
+    max_steps    : usize,
+    num_paths    : usize,
+    max_length   : usize,
+    get_neighbor : &mut Neighbor,
+  ) -> Result<Vec<Vec<Node_Id>>, Status<Node_Id>>
+    where
+      Node_Id  : Debug + Clone + Hash + Eq,
+      Cost     : Debug + Clone + Default + PartialOrd + Add<Output = Cost>,
+      Neighbor : FnMut(Node_Id, usize) -> Option<Link<Node_Id, Cost>>,
+  {
+    let (first, _) = shortest_path(
+      source.clone(),
+      destination.clone(),
+      max_cost.clone(),
+      max_steps,
+      &[],
+      &[],
+      get_neighbor
+    )?;
+
+    let mut num_valid  : usize                     = if first.len() - 1 <= max_length { 1 } else { 0 };
+    let mut found      : Vec<Vec<Node_Id>>         = vec![first];
+    let mut candidates : Vec<(Vec<Node_Id>, Cost)> = vec![];
+
+    while num_valid < num_paths {
+      let last = found[found.len() - 1].clone();
+
+      for i in 0..last.len() - 1 {
+        let spur = last[i].clone();
+        let root = &last[..=i];
+
+        //  Remove the edges that are part of the already found paths
+        //  which share the same root path.
+        //
+
+        let excluded_edges : Vec<(Node_Id, Node_Id)> =
+          found
+            .iter()
+            .filter(|p| p.len() > i + 1 && &p[..=i] == root)
+            .map(|p| (p[i].clone(), p[i + 1].clone()))
+            .collect();
+
+        //  Remove root path nodes except the spur node
+        //  to keep the path loopless.
+        //
+
+        let excluded_nodes = &last[..i];
+
+        let spur_path = match shortest_path(
+          spur,
+          destination.clone(),
+          max_cost.clone(),
+          max_steps,
+          excluded_nodes,
+          &excluded_edges,
+          get_neighbor
+        ) {
+          Ok((x, _)) => x,
+          Err(_)     => continue,
+        };
+
+        let mut total_path = last[..i].to_vec();
+        total_path.extend(spur_path);
+
+        if found.contains(&total_path) ||
+           candidates.iter().any(|(p, _)| *p == total_path) {
+          continue;
+        }
+
+        if let Some(cost) = path_cost(&total_path, get_neighbor) {
+          candidates.push((total_path, cost));
+        }
+      }
+
+      if candidates.is_empty() {
+        break;
+      }
+
+      let mut best = 0;
+      for k in 1..candidates.len() {
+        if candidates[k].1 < candidates[best].1 {
+          best = k;
+        }
+      }
+
+      let path = candidates.swap_remove(best).0;
+
+      if path.len() - 1 <= max_length {
+        num_valid += 1;
+      }
+
+      found.push(path);
+    }
+
+    found.retain(|p| p.len() - 1 <= max_length);
+
+    log_trace!("k_shortest_paths: found {} of {} paths", found.len(), num_paths);
+
+    return Ok(found);
+  }
 }
 
 pub use astar_internal::*;
@@ -400,20 +682,14 @@ mod tests {
       return None;
     };
 
-    let mut open   : Vec<Node<i64, i64>> = vec![];
-    let mut closed : Vec<Node<i64, i64>> = vec![];
-
-    open  .resize(1024, Node::default());
-    closed.resize(1024, Node::default());
-
-    let mut state = init(&mut open, 0i64, 5i64, i64::MAX);
+    let mut state = init(0i64, 5i64, i64::MAX, usize::MAX);
 
     let mut steps    = 0;
     let mut neighbor = None;
     loop {
       steps += 1;
 
-      match iteration(&mut open, &mut closed, &mut state, neighbor.clone()) {
+      match iteration(&mut state, neighbor.clone()) {
         Status::NEIGHBOR(request) => neighbor = get_neighbor(request.node, request.index),
         Status::SUCCESS           => break,
         Status::PROGRESS          => {},
@@ -421,10 +697,7 @@ mod tests {
       };
     }
 
-    let mut v : Vec<i64> = vec![];
-    v.resize(state.num_closed, 0);
-    let n = path(&closed, &state, &mut v);
-    v.resize(n, 0);
+    let v = path(&state);
 
     assert_eq!(steps, 15);
     assert_eq!(v.len(), 6);
@@ -467,32 +740,23 @@ mod tests {
       return None;
     };
 
-    let mut open   : Vec<Node<i64, i64>> = vec![Node::default()];
-    let mut closed : Vec<Node<i64, i64>> = vec![];
-
-    let mut state = init(&mut open, 0i64, 5i64, i64::MAX);
+    let mut state = init(0i64, 5i64, i64::MAX, 1);
 
     let mut steps    = 0;
     let mut neighbor = None;
     loop {
       steps += 1;
 
-      match iteration(&mut open, &mut closed, &mut state, neighbor.clone()) {
+      match iteration(&mut state, neighbor.clone()) {
         Status::NEIGHBOR(request) => neighbor = get_neighbor(request.node, request.index),
         Status::SUCCESS           => break,
         Status::PROGRESS          => {},
-        Status::OUT_OF_MEMORY     => {
-          open  .resize(open  .len() + 1024, Node::default());
-          closed.resize(closed.len() + 1024, Node::default());
-        },
+        Status::OUT_OF_MEMORY     => state.max_nodes += 1024,
         _                         => assert!(false),
       };
     }
 
-    let mut v : Vec<i64> = vec![];
-    v.resize(state.num_closed, 0);
-    let n = path(&closed, &state, &mut v);
-    v.resize(n, 0);
+    let v = path(&state);
 
     assert_eq!(steps, 16);
     assert_eq!(v.len(), 6);
@@ -535,20 +799,14 @@ mod tests {
       return None;
     };
 
-    let mut open   : Vec<Node<i64, i64>> = vec![];
-    let mut closed : Vec<Node<i64, i64>> = vec![];
-
-    open  .resize(1024, Node::default());
-    closed.resize(1024, Node::default());
-
-    let mut state = init(&mut open, 0i64, 15i64, i64::MAX);
+    let mut state = init(0i64, 15i64, i64::MAX, usize::MAX);
 
     let mut steps    = 0;
     let mut neighbor = None;
     loop {
       steps += 1;
 
-      match iteration(&mut open, &mut closed, &mut state, neighbor.clone()) {
+      match iteration(&mut state, neighbor.clone()) {
         Status::NEIGHBOR(request) => neighbor = get_neighbor(request.node, request.index),
         Status::FAIL              => break,
         Status::PROGRESS          => {},
@@ -556,10 +814,7 @@ mod tests {
       };
     }
 
-    let mut v : Vec<i64> = vec![];
-    v.resize(state.num_closed, 0);
-    let n = path(&closed, &state, &mut v);
-    v.resize(n, 0);
+    let v = path(&state);
 
     assert_eq!(steps, 25);
     assert_eq!(v.len(), 5);
@@ -601,20 +856,14 @@ mod tests {
       return None;
     };
 
-    let mut open   : Vec<Node<i64, i64>> = vec![];
-    let mut closed : Vec<Node<i64, i64>> = vec![];
-
-    open  .resize(1024, Node::default());
-    closed.resize(1024, Node::default());
-
-    let mut state = init(&mut open, 2i64, 2i64, i64::MAX);
+    let mut state = init(2i64, 2i64, i64::MAX, usize::MAX);
 
     let mut steps    = 0;
     let mut neighbor = None;
     loop {
       steps += 1;
 
-      match iteration(&mut open, &mut closed, &mut state, neighbor.clone()) {
+      match iteration(&mut state, neighbor.clone()) {
         Status::NEIGHBOR(request) => neighbor = get_neighbor(request.node, request.index),
         Status::SUCCESS           => break,
         Status::PROGRESS          => {},
@@ -622,10 +871,7 @@ mod tests {
       };
     }
 
-    let mut v : Vec<i64> = vec![];
-    v.resize(state.num_closed, 0);
-    let n = path(&closed, &state, &mut v);
-    v.resize(n, 0);
+    let v = path(&state);
 
     assert_eq!(steps, 1);
     assert_eq!(v.len(), 1);
@@ -666,20 +912,14 @@ mod tests {
       return None;
     };
 
-    let mut open   : Vec<Node<i64, i64>> = vec![];
-    let mut closed : Vec<Node<i64, i64>> = vec![];
-
-    open  .resize(1024, Node::default());
-    closed.resize(1024, Node::default());
-
-    let mut state = init(&mut open, 0i64, 5i64, i64::MAX);
+    let mut state = init(0i64, 5i64, i64::MAX, usize::MAX);
 
     let mut steps    = 0;
     let mut neighbor = None;
     loop {
       steps += 1;
 
-      match iteration(&mut open, &mut closed, &mut state, neighbor.clone()) {
+      match iteration(&mut state, neighbor.clone()) {
         Status::NEIGHBOR(request) => neighbor = get_neighbor(request.node, request.index),
         Status::SUCCESS           => break,
         Status::PROGRESS          => {},
@@ -687,10 +927,7 @@ mod tests {
       };
     }
 
-    let mut v : Vec<i64> = vec![];
-    v.resize(state.num_closed, 0);
-    let n = path(&closed, &state, &mut v);
-    v.resize(n, 0);
+    let v = path(&state);
 
     assert_eq!(steps, 19);
     assert_eq!(v.len(), 6);
@@ -701,4 +938,226 @@ mod tests {
     assert_eq!(v[4], 7);
     assert_eq!(v[5], 5);
   }
+
+  #[test]
+  fn k_shortest() {
+    let graph : Vec<((i64, i64), i64)> = vec![
+      ((0, 1),  5),
+      ((0, 2),  3),
+      ((1, 3),  4),
+      ((2, 4),  1),
+      ((3, 5), 10),
+      ((4, 6),  1),
+      ((6, 7),  1),
+      ((7, 5),  1),
+      ((2, 3),  8),
+    ];
+
+    let mut get_neighbor = |id : i64, index : usize| -> Option<Link<i64, i64>> {
+      let mut k : usize = 0;
+      for ((src, dst), cost) in graph.clone() {
+        if src == id {
+          if k == index {
+            return Some(Link::<i64, i64> {
+              neighbor       : dst,
+              exact_distance : cost,
+              estimate       : 0,
+            });
+          } else {
+            k += 1;
+          }
+        }
+      }
+      return None;
+    };
+
+    let paths = k_shortest_paths(0i64, 5i64, i64::MAX, 10000, 5, usize::MAX, &mut get_neighbor).unwrap();
+
+    assert_eq!(paths.len(), 3);
+    assert_eq!(paths[0], vec![0, 2, 4, 6, 7, 5]);
+    assert_eq!(paths[1], vec![0, 1, 3, 5]);
+    assert_eq!(paths[2], vec![0, 2, 3, 5]);
+
+    assert_eq!(path_cost(&paths[0], &mut get_neighbor), Some(7));
+    assert_eq!(path_cost(&paths[1], &mut get_neighbor), Some(19));
+    assert_eq!(path_cost(&paths[2], &mut get_neighbor), Some(21));
+  }
+
+  #[test]
+  fn k_shortest_max_length() {
+    let graph : Vec<((i64, i64), i64)> = vec![
+      ((0, 1),  5),
+      ((0, 2),  3),
+      ((1, 3),  4),
+      ((2, 4),  1),
+      ((3, 5), 10),
+      ((4, 6),  1),
+      ((6, 7),  1),
+      ((7, 5),  1),
+    ];
+
+    let mut get_neighbor = |id : i64, index : usize| -> Option<Link<i64, i64>> {
+      let mut k : usize = 0;
+      for ((src, dst), cost) in graph.clone() {
+        if src == id {
+          if k == index {
+            return Some(Link::<i64, i64> {
+              neighbor       : dst,
+              exact_distance : cost,
+              estimate       : 0,
+            });
+          } else {
+            k += 1;
+          }
+        }
+      }
+      return None;
+    };
+
+    let paths = k_shortest_paths(0i64, 5i64, i64::MAX, 10000, 5, 3, &mut get_neighbor).unwrap();
+
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0], vec![0, 1, 3, 5]);
+  }
+
+  #[test]
+  fn k_shortest_max_length_count() {
+    let graph : Vec<((i64, i64), i64)> = vec![
+      ((0, 1),  5),
+      ((0, 2),  3),
+      ((1, 3),  4),
+      ((2, 4),  1),
+      ((3, 5), 10),
+      ((4, 6),  1),
+      ((6, 7),  1),
+      ((7, 5),  1),
+      ((2, 3),  8),
+    ];
+
+    let mut get_neighbor = |id : i64, index : usize| -> Option<Link<i64, i64>> {
+      let mut k : usize = 0;
+      for ((src, dst), cost) in graph.clone() {
+        if src == id {
+          if k == index {
+            return Some(Link::<i64, i64> {
+              neighbor       : dst,
+              exact_distance : cost,
+              estimate       : 0,
+            });
+          } else {
+            k += 1;
+          }
+        }
+      }
+      return None;
+    };
+
+    //  The shortest path is too long, it should not count
+    //  towards the number of paths.
+    //
+    let paths = k_shortest_paths(0i64, 5i64, i64::MAX, 10000, 2, 3, &mut get_neighbor).unwrap();
+
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[0], vec![0, 1, 3, 5]);
+    assert_eq!(paths[1], vec![0, 2, 3, 5]);
+  }
+
+  #[test]
+  fn k_shortest_max_length_root() {
+    let graph : Vec<((i64, i64), i64)> = vec![
+      ((0, 1), 1),
+      ((1, 2), 1),
+      ((2, 3), 1),
+      ((3, 4), 1),
+      ((4, 9), 1),
+      ((0, 5), 1),
+      ((5, 6), 1),
+      ((6, 8), 1),
+      ((8, 9), 3),
+      ((5, 7), 5),
+      ((7, 9), 5),
+    ];
+
+    let mut get_neighbor = |id : i64, index : usize| -> Option<Link<i64, i64>> {
+      let mut k : usize = 0;
+      for ((src, dst), cost) in graph.clone() {
+        if src == id {
+          if k == index {
+            return Some(Link::<i64, i64> {
+              neighbor       : dst,
+              exact_distance : cost,
+              estimate       : 0,
+            });
+          } else {
+            k += 1;
+          }
+        }
+      }
+      return None;
+    };
+
+    //  The only short enough path is an alternative
+    //  of the second path, which is too long itself.
+    //
+    let paths = k_shortest_paths(0i64, 9i64, i64::MAX, 10000, 1, 3, &mut get_neighbor).unwrap();
+
+    assert_eq!(paths, vec![vec![0, 5, 7, 9]]);
+  }
+
+  #[test]
+  fn k_shortest_no_path() {
+    let graph : Vec<((i64, i64), i64)> = vec![
+      ((0, 1), 5),
+      ((1, 2), 3),
+    ];
+
+    let mut get_neighbor = |id : i64, index : usize| -> Option<Link<i64, i64>> {
+      let mut k : usize = 0;
+      for ((src, dst), cost) in graph.clone() {
+        if src == id {
+          if k == index {
+            return Some(Link::<i64, i64> {
+              neighbor       : dst,
+              exact_distance : cost,
+              estimate       : 0,
+            });
+          } else {
+            k += 1;
+          }
+        }
+      }
+      return None;
+    };
+
+    let res = k_shortest_paths(0i64, 5i64, i64::MAX, 10000, 3, usize::MAX, &mut get_neighbor);
+
+    assert_eq!(res, Err(Status::FAIL));
+  }
+
+  #[test]
+  fn large_grid() {
+    let size : i64 = 200;
+
+    let mut get_neighbor = |id : i64, index : usize| -> Option<Link<i64, i64>> {
+      let x = id % size;
+      let y = id / size;
+
+      [(x + 1, y), (x, y + 1), (x - 1, y), (x, y - 1)]
+        .iter()
+        .filter(|(nx, ny)| *nx >= 0 && *ny >= 0 && *nx < size && *ny < size)
+        .nth(index)
+        .map(|(nx, ny)| Link::<i64, i64> {
+          neighbor       : ny * size + nx,
+          exact_distance : 1,
+          estimate       : 0,
+        })
+    };
+
+    let (v, cost) = shortest_path(0i64, size * size - 1, i64::MAX, usize::MAX, &[], &[], &mut get_neighbor).unwrap();
+
+    assert_eq!(v.len(), (2 * size - 1) as usize);
+    assert_eq!(cost, 2 * size - 2);
+    assert_eq!(v[0], 0);
+    assert_eq!(v[v.len() - 1], size * size - 1);
+  }
 }
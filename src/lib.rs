@@ -1,2 +1,6 @@
 pub mod log;
-pub mod protocol; 
+pub mod protocol;
+pub mod astar;
+pub mod pagerank;
+pub mod seen;
+pub mod metrics;
@@ -1,29 +1,359 @@
 use std::{
-  sync::atomic::AtomicBool,
+  cell::RefCell,
+  env::var,
+  fs::{self, File, OpenOptions},
+  io::Write,
+  sync::atomic::{AtomicU32, Ordering},
   sync::Mutex,
+  time::Instant,
   thread
 };
 
-pub static ERROR   : AtomicBool = AtomicBool::new(true);
-pub static WARNING : AtomicBool = AtomicBool::new(true);
-pub static INFO    : AtomicBool = AtomicBool::new(true);
-pub static VERBOSE : AtomicBool = AtomicBool::new(true);
-pub static TRACE   : AtomicBool = AtomicBool::new(true);
- 
-static LOG_MUTEX : Mutex<()> = Mutex::new(());
+//  Log levels, a message is printed if the subsystem level
+//  is greater or equal to the message level.
+//
+pub const LEVEL_ERROR   : u32 = 1;
+pub const LEVEL_WARNING : u32 = 2;
+pub const LEVEL_INFO    : u32 = 3;
+pub const LEVEL_VERBOSE : u32 = 4;
+pub const LEVEL_TRACE   : u32 = 5;
 
-pub fn log_with_time(prefix : &str, message : &str) {
+pub const SUBSYSTEM_PROTOCOL   : usize = 0;
+pub const SUBSYSTEM_SERVICE    : usize = 1;
+pub const SUBSYSTEM_OPERATIONS : usize = 2;
+pub const SUBSYSTEM_ASTAR      : usize = 3;
+
+pub const SUBSYSTEMS : [&str; 4] = ["protocol", "service", "operations", "astar"];
+
+pub static LEVELS : [AtomicU32; 4] = [
+  AtomicU32::new(LEVEL_TRACE),
+  AtomicU32::new(LEVEL_TRACE),
+  AtomicU32::new(LEVEL_TRACE),
+  AtomicU32::new(LEVEL_TRACE),
+];
+
+lazy_static::lazy_static! {
+  static ref LOG_JSON : bool =
+    var("MERITRANK_LOG_FORMAT")
+      .map(|s| s == "json")
+      .unwrap_or(false);
+
+  static ref LOG_FILE : Option<String> =
+    var("MERITRANK_LOG_FILE")
+      .ok()
+      .filter(|s| !s.is_empty());
+
+  static ref LOG_FILE_MAX_SIZE : u64 =
+    var("MERITRANK_LOG_FILE_MAX_SIZE")
+      .ok()
+      .and_then(|s| s.parse::<u64>().ok())
+      .unwrap_or(10 * 1024 * 1024);
+
+  static ref LOG_FILE_MAX_FILES : usize =
+    var("MERITRANK_LOG_FILE_MAX_FILES")
+      .ok()
+      .and_then(|s| s.parse::<usize>().ok())
+      .unwrap_or(5);
+}
+
+struct Sink {
+  file   : Option<File>,
+  size   : u64,
+  failed : bool,
+}
+
+static LOG_MUTEX : Mutex<Sink> = Mutex::new(Sink {
+  file   : None,
+  size   : 0,
+  failed : false,
+});
+
+//  Request being performed by the current thread,
+//  added to log records.
+//
+#[derive(Clone, Debug, Default)]
+pub struct LogScope {
+  pub request_id : String,
+  pub command    : String,
+  pub context    : String,
+  pub begin      : Option<Instant>,
+}
+
+thread_local! {
+  static LOG_SCOPE : RefCell<LogScope> = RefCell::new(LogScope::default());
+}
+
+pub fn log_scope_begin(request_id : &str, command : &str, context : &str) {
+  LOG_SCOPE.with(|x| {
+    *x.borrow_mut() = LogScope {
+      request_id : request_id.to_string(),
+      command    : command.to_string(),
+      context    : context.to_string(),
+      begin      : Some(Instant::now()),
+    };
+  });
+}
+
+pub fn log_scope_end() {
+  LOG_SCOPE.with(|x| {
+    *x.borrow_mut() = LogScope::default();
+  });
+}
+
+pub fn log_scope() -> LogScope {
+  LOG_SCOPE.with(|x| x.borrow().clone())
+}
+
+//  Subsystem by the top-level module of the log macro call site.
+//
+pub fn subsystem_from_module(module : &str) -> usize {
+  match module.split("::").nth(1) {
+    Some("protocol")   => SUBSYSTEM_PROTOCOL,
+    Some("operations") => SUBSYSTEM_OPERATIONS,
+    Some("astar")      => SUBSYSTEM_ASTAR,
+    _                  => SUBSYSTEM_SERVICE,
+  }
+}
+
+pub fn subsystem_from_str(name : &str) -> Option<usize> {
+  SUBSYSTEMS.iter().position(|x| *x == name)
+}
+
+pub fn log_enabled(level : u32, module : &str) -> bool {
+  LEVELS[subsystem_from_module(module)].load(Ordering::Relaxed) >= level
+}
+
+pub fn set_log_level(level : u32) {
+  for x in LEVELS.iter() {
+    x.store(level, Ordering::Relaxed);
+  }
+}
+
+pub fn set_subsystem_log_level(subsystem : usize, level : u32) {
+  LEVELS[subsystem].store(level, Ordering::Relaxed);
+}
+
+//  Parse `subsystem=level` pairs separated by commas.
+//
+pub fn parse_log_levels(s : &str) -> Result<Vec<(usize, u32)>, String> {
+  let mut levels = vec![];
+
+  for item in s.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+    let (name, level) = match item.split_once('=') {
+      Some(x) => x,
+      None    => return Err(format!("Invalid log level `{}`", item)),
+    };
+
+    let subsystem = match subsystem_from_str(name.trim()) {
+      Some(x) => x,
+      None    => return Err(format!("Unknown subsystem `{}`", name)),
+    };
+
+    let level = match level.trim().parse::<u32>() {
+      Ok(x)  => x,
+      Err(_) => return Err(format!("Invalid log level `{}`", item)),
+    };
+
+    levels.push((subsystem, level));
+  }
+
+  Ok(levels)
+}
+
+//  Set log levels from `MERITRANK_LOG_LEVEL` and `MERITRANK_LOG_LEVELS`.
+//
+pub fn init_log_levels() {
+  if let Some(level) = var("MERITRANK_LOG_LEVEL").ok().and_then(|s| s.parse::<u32>().ok()) {
+    set_log_level(level);
+  }
+
+  if let Ok(s) = var("MERITRANK_LOG_LEVELS") {
+    match parse_log_levels(s.as_str()) {
+      Ok(levels) => {
+        for (subsystem, level) in levels {
+          set_subsystem_log_level(subsystem, level);
+        }
+      },
+      Err(e) => {
+        log_with_time(LEVEL_ERROR, module_path!(), format!("(init_log_levels) {}", e).as_str());
+      },
+    };
+  }
+}
+
+fn level_prefix(level : u32) -> &'static str {
+  match level {
+    LEVEL_ERROR   => "ERROR   ",
+    LEVEL_WARNING => "WARNING ",
+    LEVEL_INFO    => "INFO    ",
+    LEVEL_VERBOSE => "VERBOSE --- ",
+    _             => "TRACE   --- --- ",
+  }
+}
+
+fn level_name(level : u32) -> &'static str {
+  match level {
+    LEVEL_ERROR   => "error",
+    LEVEL_WARNING => "warning",
+    LEVEL_INFO    => "info",
+    LEVEL_VERBOSE => "verbose",
+    _             => "trace",
+  }
+}
+
+pub fn escape_json(s : &str) -> String {
+  let mut out = String::with_capacity(s.len());
+
+  for c in s.chars() {
+    match c {
+      '"'  => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\r' => out.push_str("\\r"),
+      '\t' => out.push_str("\\t"),
+      c if (c as u32) < 0x20 => out.push_str(format!("\\u{:04x}", c as u32).as_str()),
+      c    => out.push(c),
+    }
+  }
+
+  out
+}
+
+pub fn format_json(
+  time      : &str,
+  level     : u32,
+  subsystem : usize,
+  thread_id : &str,
+  scope     : &LogScope,
+  message   : &str
+) -> String {
+  let mut s = format!(
+    "{{\"time\":\"{}\",\"level\":\"{}\",\"subsystem\":\"{}\",\"thread\":\"{}\"",
+    time,
+    level_name(level),
+    SUBSYSTEMS[subsystem],
+    escape_json(thread_id)
+  );
+
+  if let Some(begin) = scope.begin {
+    s += format!(
+      ",\"request_id\":\"{}\",\"command\":\"{}\",\"context\":\"{}\",\"duration\":{}",
+      escape_json(scope.request_id.as_str()),
+      escape_json(scope.command.as_str()),
+      escape_json(scope.context.as_str()),
+      begin.elapsed().as_secs_f64()
+    ).as_str();
+  }
+
+  s + format!(",\"message\":\"{}\"}}", escape_json(message)).as_str()
+}
+
+//  Shift `path.N` to `path.N+1`, drop the oldest file
+//  and move `path` to `path.1`.
+//
+pub fn rotate_log_files(path : &str, max_files : usize) {
+  if max_files == 0 {
+    let _ = fs::remove_file(path);
+    return;
+  }
+
+  let _ = fs::remove_file(format!("{}.{}", path, max_files));
+
+  for n in (1..max_files).rev() {
+    let _ = fs::rename(format!("{}.{}", path, n), format!("{}.{}", path, n + 1));
+  }
+
+  let _ = fs::rename(path, format!("{}.1", path));
+}
+
+fn open_log_file(path : &str) -> std::io::Result<(File, u64)> {
+  let file = OpenOptions::new().create(true).append(true).open(path)?;
+  let size = file.metadata()?.len();
+  Ok((file, size))
+}
+
+fn write_line(sink : &mut Sink, line : &str) {
+  let path = match *LOG_FILE {
+    Some(ref x) if !sink.failed => x,
+    _ => {
+      println!("{}", line);
+      return;
+    },
+  };
+
+  if sink.file.is_none() {
+    match open_log_file(path) {
+      Ok((file, size)) => {
+        sink.file = Some(file);
+        sink.size = size;
+      },
+      Err(e) => {
+        sink.failed = true;
+        println!("Unable to open log file `{}`: {}", path, e);
+        println!("{}", line);
+        return;
+      },
+    };
+  }
+
+  if let Some(ref mut file) = sink.file {
+    if writeln!(file, "{}", line).is_err() {
+      println!("{}", line);
+      return;
+    }
+  }
+
+  sink.size += line.len() as u64 + 1;
+
+  if sink.size >= *LOG_FILE_MAX_SIZE {
+    sink.file = None;
+    sink.size = 0;
+    rotate_log_files(path, *LOG_FILE_MAX_FILES);
+  }
+}
+
+pub fn log_with_time(level : u32, module : &str, message : &str) {
   let time      = chrono::offset::Local::now();
-  let time_str  = time.format("%Y-%m-%d %H:%M:%S");
-  let millis    = time.timestamp_millis() % 1000;
   let thread_id = thread::current().id();
 
+  let scope     = log_scope();
+
+  let line = if *LOG_JSON {
+    format_json(
+      time.format("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string().as_str(),
+      level,
+      subsystem_from_module(module),
+      format!("{:?}", thread_id).as_str(),
+      &scope,
+      message
+    )
+  } else if scope.request_id.is_empty() {
+    format!(
+      "{}.{:03} {:3?}  {}{}",
+      time.format("%Y-%m-%d %H:%M:%S"),
+      time.timestamp_millis() % 1000,
+      thread_id,
+      level_prefix(level),
+      message
+    )
+  } else {
+    format!(
+      "{}.{:03} {:3?}  [{}] {}{}",
+      time.format("%Y-%m-%d %H:%M:%S"),
+      time.timestamp_millis() % 1000,
+      thread_id,
+      scope.request_id,
+      level_prefix(level),
+      message
+    )
+  };
+
   match LOG_MUTEX.lock() {
-    Ok(_) => {
-      println!("{}.{:03} {:3?}  {}{}", time_str, millis, thread_id, prefix, message);
+    Ok(mut sink) => {
+      write_line(&mut sink, line.as_str());
     },
     _ => {
-      println!("{}.{:03} {:3?}  LOG MUTEX FAILED", time_str, millis, thread_id);
+      println!("{}  LOG MUTEX FAILED", line);
     },
   };
 }
@@ -31,8 +361,8 @@ pub fn log_with_time(prefix : &str, message : &str) {
 #[macro_export]
 macro_rules! log_error {
   ($($arg:expr),*) => {
-    if ERROR.load(Ordering::Relaxed) {
-      log_with_time("ERROR   ", format!($($arg),*).as_str());
+    if log_enabled(LEVEL_ERROR, module_path!()) {
+      log_with_time(LEVEL_ERROR, module_path!(), format!($($arg),*).as_str());
     }
   };
 }
@@ -40,8 +370,8 @@ macro_rules! log_error {
 #[macro_export]
 macro_rules! log_warning {
   ($($arg:expr),*) => {
-    if WARNING.load(Ordering::Relaxed) {
-      log_with_time("WARNING ", format!($($arg),*).as_str());
+    if log_enabled(LEVEL_WARNING, module_path!()) {
+      log_with_time(LEVEL_WARNING, module_path!(), format!($($arg),*).as_str());
     }
   };
 }
@@ -49,8 +379,8 @@ macro_rules! log_warning {
 #[macro_export]
 macro_rules! log_info {
   ($($arg:expr),*) => {
-    if INFO.load(Ordering::Relaxed) {
-      log_with_time("INFO    ", format!($($arg),*).as_str());
+    if log_enabled(LEVEL_INFO, module_path!()) {
+      log_with_time(LEVEL_INFO, module_path!(), format!($($arg),*).as_str());
     }
   };
 }
@@ -58,8 +388,8 @@ macro_rules! log_info {
 #[macro_export]
 macro_rules! log_verbose {
   ($($arg:expr),*) => {
-    if VERBOSE.load(Ordering::Relaxed) {
-      log_with_time("VERBOSE --- ", format!($($arg),*).as_str());
+    if log_enabled(LEVEL_VERBOSE, module_path!()) {
+      log_with_time(LEVEL_VERBOSE, module_path!(), format!($($arg),*).as_str());
     }
   };
 }
@@ -67,8 +397,74 @@ macro_rules! log_verbose {
 #[macro_export]
 macro_rules! log_trace {
   ($($arg:expr),*) => {
-    if TRACE.load(Ordering::Relaxed) {
-      log_with_time("TRACE   --- --- ", format!($($arg),*).as_str());
+    if log_enabled(LEVEL_TRACE, module_path!()) {
+      log_with_time(LEVEL_TRACE, module_path!(), format!($($arg),*).as_str());
     }
   };
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn subsystems() {
+    assert_eq!(subsystem_from_module("meritrank_service::protocol"),   SUBSYSTEM_PROTOCOL);
+    assert_eq!(subsystem_from_module("meritrank_service::operations"), SUBSYSTEM_OPERATIONS);
+    assert_eq!(subsystem_from_module("meritrank_service::astar"),      SUBSYSTEM_ASTAR);
+    assert_eq!(subsystem_from_module("meritrank_service::astar::astar_internal"), SUBSYSTEM_ASTAR);
+    assert_eq!(subsystem_from_module("meritrank_service::service"),    SUBSYSTEM_SERVICE);
+    assert_eq!(subsystem_from_module("meritrank_service"),             SUBSYSTEM_SERVICE);
+  }
+
+  #[test]
+  fn levels_parse() {
+    assert_eq!(
+      parse_log_levels("operations=5, astar=0,").unwrap(),
+      vec![(SUBSYSTEM_OPERATIONS, 5), (SUBSYSTEM_ASTAR, 0)]
+    );
+    assert!(parse_log_levels("network=1").is_err());
+    assert!(parse_log_levels("service").is_err());
+    assert!(parse_log_levels("service=x").is_err());
+  }
+
+  #[test]
+  fn json_record() {
+    let scope = LogScope {
+      request_id : "42".to_string(),
+      command    : "put_edge".to_string(),
+      context    : "X".to_string(),
+      begin      : Some(Instant::now()),
+    };
+
+    let s = format_json("T", LEVEL_WARNING, SUBSYSTEM_SERVICE, "ThreadId(2)", &scope, "a \"b\"\n");
+
+    assert!(s.starts_with("{\"time\":\"T\",\"level\":\"warning\",\"subsystem\":\"service\",\"thread\":\"ThreadId(2)\",\"request_id\":\"42\",\"command\":\"put_edge\",\"context\":\"X\",\"duration\":"));
+    assert!(s.ends_with(",\"message\":\"a \\\"b\\\"\\n\"}"));
+
+    let s = format_json("T", LEVEL_ERROR, SUBSYSTEM_ASTAR, "1", &LogScope::default(), "\u{1}");
+
+    assert_eq!(s, "{\"time\":\"T\",\"level\":\"error\",\"subsystem\":\"astar\",\"thread\":\"1\",\"message\":\"\\u0001\"}");
+  }
+
+  #[test]
+  fn log_rotation() {
+    let dir  = std::env::temp_dir().join(format!("meritrank_log_rotation_{}", std::process::id()));
+    let _    = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("service.log");
+    let path = path.to_str().unwrap();
+
+    for n in 0..3 {
+      fs::write(path, format!("{}", n)).unwrap();
+      rotate_log_files(path, 2);
+    }
+
+    assert!(!std::path::Path::new(path).exists());
+    assert_eq!(fs::read_to_string(format!("{}.1", path)).unwrap(), "2");
+    assert_eq!(fs::read_to_string(format!("{}.2", path)).unwrap(), "1");
+    assert!(!std::path::Path::new(format!("{}.3", path).as_str()).exists());
+
+    let _ = fs::remove_dir_all(&dir);
+  }
+}
@@ -1,6 +1,9 @@
 pub mod log;
 pub mod protocol;
 pub mod astar;
+pub mod pagerank;
+pub mod seen;
+pub mod metrics;
 pub mod operations;
 pub mod service;
 
@@ -0,0 +1,225 @@
+//  ================================================================
+//
+//    metrics.rs
+//
+//  Command counters and latency histograms,
+//  rendered in the Prometheus text format
+//
+//  ================================================================
+
+use std::{
+  collections::BTreeMap,
+  fmt::Write,
+  sync::Mutex,
+};
+use crate::protocol::ALL_COMMANDS;
+
+//  Upper bounds of histogram buckets in seconds
+//
+pub const DURATION_BUCKETS : [f64; 10] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 60.0];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Histogram {
+  pub buckets : [u64; DURATION_BUCKETS.len()],
+  pub count   : u64,
+  pub sum     : f64,
+}
+
+impl Histogram {
+  pub fn observe(&mut self, seconds : f64) {
+    for (bucket, bound) in self.buckets.iter_mut().zip(DURATION_BUCKETS.iter()) {
+      if seconds <= *bound {
+        *bucket += 1;
+      }
+    }
+    self.count += 1;
+    self.sum   += seconds;
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandMetrics {
+  pub count   : u64,
+  pub errors  : u64,
+  pub latency : Histogram,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metrics {
+  pub commands : BTreeMap<String, CommandMetrics>,
+  pub recalc   : BTreeMap<String, Histogram>,
+}
+
+impl Metrics {
+  pub const fn new() -> Metrics {
+    Metrics {
+      commands : BTreeMap::new(),
+      recalc   : BTreeMap::new(),
+    }
+  }
+}
+
+static METRICS : Mutex<Metrics> = Mutex::new(Metrics::new());
+
+//  Command ids come from clients, so unknown ones share one label
+//  to keep the number of series bounded.
+//
+pub const UNKNOWN_COMMAND : &str = "unknown";
+
+pub fn command_label(id : &str) -> &str {
+  if ALL_COMMANDS.contains(&id) {
+    id
+  } else {
+    UNKNOWN_COMMAND
+  }
+}
+
+pub fn observe_command(id : &str, seconds : f64, ok : bool) {
+  if let Ok(mut metrics) = METRICS.lock() {
+    let x = metrics.commands.entry(command_label(id).to_string()).or_default();
+    x.count += 1;
+    if !ok {
+      x.errors += 1;
+    }
+    x.latency.observe(seconds);
+  }
+}
+
+pub fn observe_recalc(context : &str, seconds : f64) {
+  if let Ok(mut metrics) = METRICS.lock() {
+    metrics.recalc.entry(context.to_string()).or_default().observe(seconds);
+  }
+}
+
+pub fn snapshot() -> Metrics {
+  match METRICS.lock() {
+    Ok(x)  => x.clone(),
+    Err(_) => Metrics::new(),
+  }
+}
+
+//  ================================================================
+//
+//    Prometheus text format
+//
+//  ================================================================
+
+fn escape_label(s : &str) -> String {
+  s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn header(out : &mut String, name : &str, kind : &str, help : &str) {
+  let _ = writeln!(out, "# HELP {} {}", name, help);
+  let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn histogram(out : &mut String, name : &str, label : &str, value : &str, x : &Histogram) {
+  let value = escape_label(value);
+
+  for (bucket, bound) in x.buckets.iter().zip(DURATION_BUCKETS.iter()) {
+    let _ = writeln!(out, "{}_bucket{{{}=\"{}\",le=\"{}\"}} {}", name, label, value, bound, bucket);
+  }
+  let _ = writeln!(out, "{}_bucket{{{}=\"{}\",le=\"+Inf\"}} {}", name, label, value, x.count);
+  let _ = writeln!(out, "{}_sum{{{}=\"{}\"}} {}", name, label, value, x.sum);
+  let _ = writeln!(out, "{}_count{{{}=\"{}\"}} {}", name, label, value, x.count);
+}
+
+//  `graph_sizes` is `(context, nodes, edges)`.
+//
+pub fn render(
+  metrics     : &Metrics,
+  queue_depth : usize,
+  graph_sizes : &[(String, usize, usize)]
+) -> String {
+  let mut out = String::new();
+
+  header(&mut out, "meritrank_commands_total", "counter", "Number of commands performed.");
+  for (id, x) in metrics.commands.iter() {
+    let _ = writeln!(out, "meritrank_commands_total{{command=\"{}\"}} {}", escape_label(id), x.count);
+  }
+
+  header(&mut out, "meritrank_command_errors_total", "counter", "Number of commands failed.");
+  for (id, x) in metrics.commands.iter() {
+    let _ = writeln!(out, "meritrank_command_errors_total{{command=\"{}\"}} {}", escape_label(id), x.errors);
+  }
+
+  header(&mut out, "meritrank_command_duration_seconds", "histogram", "Command latency.");
+  for (id, x) in metrics.commands.iter() {
+    histogram(&mut out, "meritrank_command_duration_seconds", "command", id, &x.latency);
+  }
+
+  header(&mut out, "meritrank_queue_depth", "gauge", "Number of write commands waiting in the queue.");
+  let _ = writeln!(out, "meritrank_queue_depth {}", queue_depth);
+
+  header(&mut out, "meritrank_graph_nodes", "gauge", "Number of nodes by context.");
+  for (context, nodes, _) in graph_sizes.iter() {
+    let _ = writeln!(out, "meritrank_graph_nodes{{context=\"{}\"}} {}", escape_label(context), nodes);
+  }
+
+  header(&mut out, "meritrank_graph_edges", "gauge", "Number of edges by context.");
+  for (context, _, edges) in graph_sizes.iter() {
+    let _ = writeln!(out, "meritrank_graph_edges{{context=\"{}\"}} {}", escape_label(context), edges);
+  }
+
+  header(&mut out, "meritrank_recalculation_duration_seconds", "histogram", "Zero node recalculation duration.");
+  for (context, x) in metrics.recalc.iter() {
+    histogram(&mut out, "meritrank_recalculation_duration_seconds", "context", context, x);
+  }
+
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn histogram_buckets() {
+    let mut x = Histogram::default();
+
+    x.observe(0.002);
+    x.observe(0.2);
+    x.observe(100.0);
+
+    assert_eq!(x.count, 3);
+    assert_eq!(x.buckets[0], 0);
+    assert_eq!(x.buckets[1], 1);
+    assert_eq!(x.buckets[5], 2);
+    assert_eq!(x.buckets[DURATION_BUCKETS.len() - 1], 2);
+    assert!((x.sum - 100.202).abs() < 1e-9);
+  }
+
+  #[test]
+  fn unknown_command_label() {
+    assert_eq!(command_label("put_edge"), "put_edge");
+    assert_eq!(command_label("metrics"),  "metrics");
+    assert_eq!(command_label("no_such_command"), UNKNOWN_COMMAND);
+    assert_eq!(command_label(""), UNKNOWN_COMMAND);
+  }
+
+  #[test]
+  fn render_text() {
+    let mut metrics = Metrics::new();
+
+    let put_edge = metrics.commands.entry("put_edge".to_string()).or_default();
+    put_edge.count  = 2;
+    put_edge.errors = 1;
+    put_edge.latency.observe(0.01);
+    put_edge.latency.observe(0.02);
+
+    metrics.recalc.entry("".to_string()).or_default().observe(3.0);
+
+    let text = render(&metrics, 5, &[("X\"".to_string(), 10, 20)]);
+
+    assert!(text.contains("# TYPE meritrank_commands_total counter\n"));
+    assert!(text.contains("meritrank_commands_total{command=\"put_edge\"} 2\n"));
+    assert!(text.contains("meritrank_command_errors_total{command=\"put_edge\"} 1\n"));
+    assert!(text.contains("meritrank_command_duration_seconds_bucket{command=\"put_edge\",le=\"0.01\"} 1\n"));
+    assert!(text.contains("meritrank_command_duration_seconds_bucket{command=\"put_edge\",le=\"+Inf\"} 2\n"));
+    assert!(text.contains("meritrank_command_duration_seconds_count{command=\"put_edge\"} 2\n"));
+    assert!(text.contains("meritrank_queue_depth 5\n"));
+    assert!(text.contains("meritrank_graph_nodes{context=\"X\\\"\"} 10\n"));
+    assert!(text.contains("meritrank_graph_edges{context=\"X\\\"\"} 20\n"));
+    assert!(text.contains("meritrank_recalculation_duration_seconds_count{context=\"\"} 1\n"));
+  }
+}
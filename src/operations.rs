@@ -1,8 +1,8 @@
 use std::{
-  sync::atomic::Ordering,
-  collections::HashMap,
+  collections::{HashMap, HashSet, VecDeque},
   env::var,
   string::ToString,
+  time::{SystemTime, UNIX_EPOCH},
 };
 use petgraph::{visit::EdgeRef, graph::{DiGraph, NodeIndex}};
 use simple_pagerank::Pagerank;
@@ -15,6 +15,9 @@ use crate::log_verbose;
 use crate::log_trace;
 use crate::log::*;
 use crate::astar::*;
+use crate::pagerank::*;
+use crate::seen::*;
+use crate::metrics::observe_recalc;
 
 pub use meritrank::Weight;
 
@@ -46,6 +49,13 @@ lazy_static::lazy_static! {
       .and_then(|s| s.parse::<usize>().ok())
       .unwrap_or(100);
 
+  pub static ref RECALC_THREADS : usize =
+    var("MERITRANK_RECALC_THREADS")
+      .ok()
+      .and_then(|s| s.parse::<usize>().ok())
+      .map(|n| std::cmp::max(n, 1))
+      .unwrap_or(1);
+
   pub static ref FILTER_NUM_HASHES : usize =
     var("MERITRANK_FILTER_NUM_HASHES")
       .ok()
@@ -64,6 +74,92 @@ lazy_static::lazy_static! {
       .ok()
       .and_then(|s| s.parse::<usize>().ok())
       .unwrap_or(8192);
+
+  pub static ref FILTER_KIND : SeenFilterKind =
+    var("MERITRANK_FILTER_KIND")
+      .ok()
+      .and_then(|s| seen_filter_kind_from_str(&s))
+      .unwrap_or(SeenFilterKind::Bloom);
+
+  //  Zero nodes for other contexts, `context=node[:top_nodes_limit]`
+  //  separated by commas.
+  //
+  pub static ref ZERO_NODES : String =
+    var("MERITRANK_ZERO_NODES")
+      .unwrap_or("".to_string());
+
+  pub static ref ZERO_INCREMENTAL : bool =
+    var("MERITRANK_ZERO_INCREMENTAL")
+      .ok()
+      .and_then(|s| s.parse::<bool>().ok())
+      .unwrap_or(false);
+
+  pub static ref CENTRALITY : String =
+    var("MERITRANK_CENTRALITY")
+      .unwrap_or("pagerank".to_string());
+
+  pub static ref PAGERANK_DAMPING : f64 =
+    var("MERITRANK_PAGERANK_DAMPING")
+      .ok()
+      .and_then(|s| s.parse::<f64>().ok())
+      .unwrap_or(0.85);
+
+  pub static ref PAGERANK_TOLERANCE : f64 =
+    var("MERITRANK_PAGERANK_TOLERANCE")
+      .ok()
+      .and_then(|s| s.parse::<f64>().ok())
+      .unwrap_or(1e-6);
+
+  pub static ref PAGERANK_MAX_ITERATIONS : usize =
+    var("MERITRANK_PAGERANK_MAX_ITERATIONS")
+      .ok()
+      .and_then(|s| s.parse::<usize>().ok())
+      .unwrap_or(100);
+
+  pub static ref PAGERANK_SEEDS : Vec<String> =
+    var("MERITRANK_PAGERANK_SEEDS")
+      .unwrap_or("".to_string())
+      .split(',')
+      .map(|s| s.trim().to_string())
+      .filter(|s| !s.is_empty())
+      .collect();
+
+  //  Half-life of edge weights in seconds, `context=seconds`
+  //  separated by commas.
+  //
+  pub static ref DECAY_HALF_LIFE : String =
+    var("MERITRANK_DECAY_HALF_LIFE")
+      .unwrap_or("".to_string());
+
+  //  Half-life of edge weights in seconds by destination node kind,
+  //  `kind=seconds` separated by commas, kind is `U`, `B` or `C`.
+  //
+  pub static ref DECAY_HALF_LIFE_KINDS : String =
+    var("MERITRANK_DECAY_HALF_LIFE_KINDS")
+      .unwrap_or("".to_string());
+
+  pub static ref GRAPH_NUM_PATHS : u32 =
+    var("MERITRANK_GRAPH_NUM_PATHS")
+      .ok()
+      .and_then(|s| s.parse::<u32>().ok())
+      .and_then(|n| Some(std::cmp::max(n, 1)))
+      .unwrap_or(1);
+
+  pub static ref GRAPH_MAX_PATH_LENGTH : u32 =
+    var("MERITRANK_GRAPH_MAX_PATH_LENGTH")
+      .ok()
+      .and_then(|s| s.parse::<u32>().ok())
+      .unwrap_or(u32::MAX);
+
+  pub static ref GRAPH_PATH_COST : String =
+    var("MERITRANK_GRAPH_PATH_COST")
+      .unwrap_or("positive".to_string());
+
+  pub static ref ASTAR_MAX_STEPS : usize =
+    var("MERITRANK_ASTAR_MAX_STEPS")
+      .ok()
+      .and_then(|s| s.parse::<usize>().ok())
+      .unwrap_or(usize::MAX);
 }
 
 //  ================================================================
@@ -72,7 +168,7 @@ lazy_static::lazy_static! {
 //
 //  ================================================================
 
-#[derive(PartialEq, Eq, Clone, Copy, Default)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Default)]
 pub enum NodeKind {
   #[default]
   Unknown,
@@ -81,13 +177,86 @@ pub enum NodeKind {
   Comment,
 }
 
+//  Edge cost model for the path search
+//
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum PathCost {
+  //  Positive edges only
+  #[default]
+  PositiveOnly,
+
+  //  Positive and negative edges, by absolute weight
+  Signed,
+
+  //  Positive edges, weighted by the ego's scores of the target nodes
+  ScoreWeighted,
+}
+
+//  Zero node of a context
+//
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ZeroNode {
+  pub name            : String,
+  pub top_nodes_limit : usize,
+}
+
 #[derive(PartialEq, Eq, Clone, Default)]
 pub struct NodeInfo {
   pub kind       : NodeKind,
   pub name       : String,
 
-  // Bloom filter of nodes marked as seen by this node
-  pub seen_nodes : Vec<u64>,
+  // Filter of nodes marked as seen by this node
+  pub seen_nodes : Option<SeenFilter>,
+}
+
+//  Half-life of edge weights in seconds, by context and by
+//  destination node kind. Context half-life takes precedence.
+//
+#[derive(PartialEq, Clone, Default)]
+pub struct Decay {
+  pub by_context : HashMap<String, f64>,
+  pub by_kind    : HashMap<NodeKind, f64>,
+}
+
+//  Raw weight and timestamp by edge
+//
+pub type EdgeStamps = HashMap<(NodeId, NodeId), (Weight, u64)>;
+
+//  Raw edge weight set at the moment
+//
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct EdgeChange {
+  pub src       : NodeId,
+  pub dst       : NodeId,
+  pub amount    : Weight,
+  pub timestamp : u64,
+}
+
+//  Options of `read_graph_paths`
+//
+#[derive(Clone, Debug)]
+pub struct GraphQuery<'a> {
+  pub positive_only   : bool,
+  pub path_cost       : &'a str,
+  pub num_paths       : u32,
+  pub max_path_length : u32,
+  pub index           : u32,
+  pub count           : u32,
+}
+
+//  Seen filter of a node, and the aggregate over all nodes,
+//  see `read_seen_filter_stats`
+//
+pub type SeenFilterStats  = (String, String, u64, u64, f64, f64);
+pub type SeenFilterTotals = (u64, u64, f64, f64, f64);
+
+//  Ranks of an ego sorted by absolute score, with the set of
+//  nodes visited by the ego's walks.
+//
+#[derive(Clone, Default)]
+pub struct RanksCache {
+  pub ranks   : Vec<(NodeId, NodeKind, Weight)>,
+  pub visited : HashSet<NodeId>,
 }
 
 //  Augmented multi-context graph
@@ -100,89 +269,329 @@ pub struct AugMultiGraph {
   pub dummy_graph : MeritRank,
   pub node_ids    : HashMap<String, NodeId>,
   pub contexts    : HashMap<String, MeritRank>,
+
+  //  Zero nodes by context.
+  //
+  //  For each context, nodes with edges changed since the last zero
+  //  node recalculation, and cached user ranks from the last recalculation.
+  //
+  pub zero_nodes  : HashMap<String, ZeroNode>,
+  pub zero_dirty  : HashMap<String, HashSet<NodeId>>,
+  pub zero_ranks  : HashMap<String, HashMap<NodeId, Vec<(NodeId, Weight)>>>,
+
+  //  Number of walks each node was calculated with, by context.
+  //
+  //  Walks upgraded on a copy of the graph are lost on the next
+  //  `copy_from`, so they are kept as context, node name and number
+  //  of walks until the service applies them to the original.
+  //
+  pub walk_counts   : HashMap<String, HashMap<NodeId, usize>>,
+  pub walk_upgrades : Vec<(String, String, usize)>,
+
+  //  Number of threads for the zero node recalculation.
+  //
+  pub recalc_threads : usize,
+
+  //  Centrality for the zero node top nodes, and trusted users
+  //  for the personalized centrality.
+  //
+  pub centrality     : String,
+  pub pagerank_seeds : Vec<String>,
+
+  //  Cached ranks by context and ego.
+  //
+  //  Each edge change gets a sequence number. The last change of
+  //  each source node is kept by context, so a copy of the graph
+  //  can drop only the cached ranks affected by newer changes.
+  //
+  pub ranks_cache : HashMap<String, HashMap<NodeId, RanksCache>>,
+  pub edge_seq    : u64,
+  pub reset_seq   : u64,
+  pub node_seq    : HashMap<String, HashMap<NodeId, u64>>,
+
+  //  Raw edge weights with timestamps by context. The graphs hold
+  //  effective weights, with the decay applied.
+  //
+  pub decay       : Decay,
+  pub edge_stamps : HashMap<String, EdgeStamps>,
+
+  //  Append-only history of edge changes by context.
+  //
+  pub edge_history : HashMap<String, Vec<EdgeChange>>,
 }
 
 //  ================================================================
 //
-//    Bloom filter
+//    Utils
 //
 //  ================================================================
 
-use std::hash::Hasher;
-use std::collections::hash_map::DefaultHasher;
+pub fn kind_from_name(name : &str) -> NodeKind {
+  log_trace!("kind_from_name: `{}`", name);
+
+  match name.chars().nth(0) {
+    Some('U') => NodeKind::User,
+    Some('B') => NodeKind::Beacon,
+    Some('C') => NodeKind::Comment,
+    _         => NodeKind::Unknown,
+  }
+}
 
-pub fn bloom_filter_bits(
-  size       : usize,
-  num_hashes : usize,
-  id         : usize
-) -> Vec<u64> {
-  let mut v : Vec<u64> = vec![];
-  v.resize(size, 0);
+pub fn zero_node_from_str(s : &str) -> Option<(String, ZeroNode)> {
+  let (context, node) = s.split_once('=')?;
 
-  for n in 1..=num_hashes {
-    let mut h = DefaultHasher::new();
-    h.write_u16( n as u16);
-    h.write_u64(id as u64);
-    let hash = h.finish();
+  let (name, top_nodes_limit) = match node.split_once(':') {
+    Some((name, limit)) => (name, limit.trim().parse::<usize>().ok()?),
+    None                => (node, *TOP_NODES_LIMIT),
+  };
 
-    let u64_index = ((hash / 64u64) as usize) % size;
-    let bit_index =   hash % 64u64;
+  let name = name.trim();
 
-    v[u64_index] |= 1u64 << bit_index;
+  if kind_from_name(name) != NodeKind::User {
+    return None;
   }
 
-  v
+  Some((context.trim().to_string(), ZeroNode {
+    name : name.to_string(),
+    top_nodes_limit,
+  }))
+}
+
+//  Difference between old and new zero node edges,
+//  as `(node, old weight, new weight)` sorted by weight change.
+//
+pub fn zero_edges_diff(
+  old_edges : &HashMap<NodeId, Weight>,
+  new_edges : &HashMap<NodeId, Weight>
+) -> Vec<(NodeId, Weight, Weight)> {
+  let mut diff : Vec<(NodeId, Weight, Weight)> =
+    old_edges
+      .keys()
+      .chain(new_edges.keys().filter(|id| !old_edges.contains_key(id)))
+      .map(|id| (
+        *id,
+        *old_edges.get(id).unwrap_or(&0.0),
+        *new_edges.get(id).unwrap_or(&0.0)
+      ))
+      .filter(|(_, old_amount, new_amount)| (old_amount - new_amount).abs() > EPSILON)
+      .collect();
+
+  diff.sort_by(|(id_a, old_a, new_a), (id_b, old_b, new_b)|
+    (new_b - old_b).abs().total_cmp(&(new_a - old_a).abs()).then(id_a.cmp(id_b))
+  );
+
+  diff
 }
 
-pub fn bloom_filter_add(
-  mask : &mut [u64],
-  bits : &[u64]
+//  Drop cached ranks of egos whose walks visit the node.
+//
+pub fn invalidate_ranks(
+  cache   : &mut HashMap<String, HashMap<NodeId, RanksCache>>,
+  context : &str,
+  node_id : NodeId
 ) {
-  if mask.len() != bits.len() {
-    log_error!("(bloom_filter_add) Invalid arguments");
-    return;
+  if let Some(egos) = cache.get_mut(context) {
+    egos.retain(|ego, ranks| *ego != node_id && !ranks.visited.contains(&node_id));
+  }
+}
+
+//  Calculate ranks for each ego, distributing egos across threads.
+//  Each thread works on its own copy of the graph.
+//
+pub fn calculate_ranks_parallel(
+  graph       : &mut MeritRank,
+  egos        : &[NodeId],
+  num_walk    : usize,
+  num_threads : usize
+) -> Vec<(NodeId, Vec<(NodeId, Weight)>)> {
+  log_trace!("calculate_ranks_parallel: {} {} {}", egos.len(), num_walk, num_threads);
+
+  let calculate_ranks = |graph : &mut MeritRank, egos : &[NodeId]| -> Vec<(NodeId, Vec<(NodeId, Weight)>)> {
+    egos
+      .iter()
+      .filter_map(|id| {
+        if let Err(e) = graph.calculate(*id, num_walk) {
+          log_error!("(calculate_ranks_parallel) {}", e);
+          return None;
+        }
+        match graph.get_ranks(*id, None) {
+          Ok(ranks) => Some((*id, ranks)),
+          Err(e)    => {
+            log_error!("(calculate_ranks_parallel) {}", e);
+            None
+          },
+        }
+      })
+      .collect()
+  };
+
+  let num_threads = std::cmp::min(num_threads, egos.len());
+
+  if num_threads <= 1 {
+    return calculate_ranks(graph, egos);
   }
 
-  for i in 0..mask.len() {
-    mask[i] |= bits[i];
+  let chunk_size = egos.len().div_ceil(num_threads);
+
+  std::thread::scope(|scope| {
+    let threads : Vec<_> =
+      egos
+        .chunks(chunk_size)
+        .map(|chunk| {
+          let mut graph_cloned = graph.clone();
+          scope.spawn(move || calculate_ranks(&mut graph_cloned, chunk))
+        })
+        .collect();
+
+    threads
+      .into_iter()
+      .flat_map(|thread| match thread.join() {
+        Ok(ranks) => ranks,
+        Err(_)    => {
+          log_error!("(calculate_ranks_parallel) Thread panicked");
+          vec![]
+        },
+      })
+      .collect()
+  })
+}
+
+pub fn default_zero_nodes() -> HashMap<String, ZeroNode> {
+  let mut zero_nodes = HashMap::new();
+
+  zero_nodes.insert("".to_string(), ZeroNode {
+    name            : ZERO_NODE.clone(),
+    top_nodes_limit : *TOP_NODES_LIMIT,
+  });
+
+  for s in ZERO_NODES.split(',').filter(|s| !s.trim().is_empty()) {
+    match zero_node_from_str(s) {
+      Some((context, zero)) => { zero_nodes.insert(context, zero); },
+      None                  => log_error!("(default_zero_nodes) Invalid zero node: `{}`", s),
+    };
   }
+
+  zero_nodes
+}
+
+pub fn new_seen_filter() -> SeenFilter {
+  SeenFilter::new(*FILTER_KIND, *FILTER_MIN_SIZE, *FILTER_NUM_HASHES)
 }
 
-pub fn bloom_filter_contains(
-  mask : &[u64],
-  bits : &[u64]
-) -> bool {
-  if mask.len() != bits.len() {
-    log_error!("(bloom_filter_contains) Invalid arguments");
-    return false;
+pub fn kind_from_str(s : &str) -> Option<NodeKind> {
+  match s {
+    "U" => Some(NodeKind::User),
+    "B" => Some(NodeKind::Beacon),
+    "C" => Some(NodeKind::Comment),
+    _   => None,
   }
+}
 
-  for i in 0..mask.len() {
-    if (mask[i] & bits[i]) != bits[i] {
-      return false;
-    }
+pub fn default_decay() -> Decay {
+  let mut decay = Decay::default();
+
+  for s in DECAY_HALF_LIFE.split(',').filter(|s| !s.trim().is_empty()) {
+    match s.split_once('=').and_then(|(context, x)| Some((context.trim(), x.trim().parse::<f64>().ok()?))) {
+      Some((context, half_life)) => { decay.by_context.insert(context.to_string(), half_life); },
+      None                       => log_error!("(default_decay) Invalid half-life: `{}`", s),
+    };
+  }
+
+  for s in DECAY_HALF_LIFE_KINDS.split(',').filter(|s| !s.trim().is_empty()) {
+    match s.split_once('=').and_then(|(kind, x)| Some((kind_from_str(kind.trim())?, x.trim().parse::<f64>().ok()?))) {
+      Some((kind, half_life)) => { decay.by_kind.insert(kind, half_life); },
+      None                    => log_error!("(default_decay) Invalid half-life: `{}`", s),
+    };
   }
 
-  return true;
+  decay
 }
 
-//  ================================================================
-//
-//    Utils
+//  Weight decayed by half for each half-life passed since the timestamp.
 //
-//  ================================================================
+pub fn decayed_weight(
+  amount    : Weight,
+  half_life : Option<f64>,
+  timestamp : u64,
+  now       : u64
+) -> Weight {
+  match half_life {
+    Some(x) if x > 0.0 => amount * 0.5_f64.powf(now.saturating_sub(timestamp) as f64 / x),
+    _                  => amount,
+  }
+}
 
-pub fn kind_from_name(name : &str) -> NodeKind {
-  log_trace!("kind_from_name: `{}`", name);
+pub fn now_seconds() -> u64 {
+  SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
 
-  match name.chars().nth(0) {
-    Some('U') => NodeKind::User,
-    Some('B') => NodeKind::Beacon,
-    Some('C') => NodeKind::Comment,
-    _         => NodeKind::Unknown,
+pub fn path_cost_from_str(s : &str) -> Option<PathCost> {
+  match s {
+    ""         => Some(PathCost::PositiveOnly),
+    "positive" => Some(PathCost::PositiveOnly),
+    "signed"   => Some(PathCost::Signed),
+    "score"    => Some(PathCost::ScoreWeighted),
+    _          => None,
   }
 }
 
+//  Outgoing links of the node with costs for the path search.
+//  Cost is the inverse of the normalized edge weight. With negative
+//  edges, weights are normalized by the sum of absolute weights.
+//
+fn path_links(
+  graph     : &Graph,
+  node      : NodeId,
+  path_cost : PathCost,
+  scores    : &HashMap<NodeId, Weight>
+) -> Vec<Link<NodeId, Weight>> {
+  let data = match graph.get_node_data(node) {
+    Some(x) => x,
+    None    => return vec![],
+  };
+
+  let edges : Vec<(NodeId, Weight)> = match path_cost {
+    PathCost::Signed => {
+      let abs_sum : Weight =
+        data.pos_edges.values()
+          .chain(data.neg_edges.values())
+          .map(|w| w.abs())
+          .sum();
+
+      let abs_sum = if abs_sum > EPSILON { abs_sum } else { 1.0 };
+
+      data.pos_edges.iter()
+        .chain(data.neg_edges.iter())
+        .map(|(n, w)| (*n, w.abs() / abs_sum))
+        .collect()
+    },
+
+    _ => {
+      let pos_sum = if data.pos_sum > EPSILON { data.pos_sum } else { 1.0 };
+
+      data.pos_edges.iter()
+        .map(|(n, w)| (*n, *w / pos_sum))
+        .collect()
+    },
+  };
+
+  edges
+    .into_iter()
+    .map(|(n, w)| {
+      let w = match path_cost {
+        PathCost::ScoreWeighted => w * *scores.get(&n).unwrap_or(&0.0),
+        _                       => w,
+      };
+
+      Link::<NodeId, Weight> {
+        neighbor       : n,
+        exact_distance : if w < EPSILON { 1_000_000.0 } else { 1.0 / w },
+        estimate       : 0.0,
+      }
+    })
+    .collect()
+}
+
 impl Default for AugMultiGraph {
   fn default() -> AugMultiGraph {
     AugMultiGraph::new()
@@ -204,14 +613,101 @@ impl AugMultiGraph {
       dummy_graph : MeritRank::new(Graph::new()),
       node_ids    : HashMap::new(),
       contexts    : HashMap::new(),
+      zero_nodes  : default_zero_nodes(),
+      zero_dirty  : HashMap::new(),
+      zero_ranks  : HashMap::new(),
+      walk_counts : HashMap::new(),
+      walk_upgrades : Vec::new(),
+      recalc_threads : *RECALC_THREADS,
+      centrality     : CENTRALITY.clone(),
+      pagerank_seeds : PAGERANK_SEEDS.clone(),
+      ranks_cache : HashMap::new(),
+      edge_seq    : 0,
+      reset_seq   : 0,
+      node_seq    : HashMap::new(),
+      decay       : default_decay(),
+      edge_stamps : HashMap::new(),
+      edge_history : HashMap::new(),
     }
   }
 
   pub fn copy_from(&mut self, other : &AugMultiGraph) {
+    //  Keep cached zero node ranks, and mark nodes with edges changed
+    //  since the last copy, so a copy that is kept between zero node
+    //  recalculations can recalculate incrementally.
+    //
+    if other.reset_seq > self.edge_seq {
+      self.zero_dirty.clear();
+      self.zero_ranks.clear();
+    } else {
+      for (context, zero) in other.zero_nodes.iter() {
+        if self.zero_nodes.get(context) != Some(zero) {
+          self.zero_dirty.remove(context);
+          self.zero_ranks.remove(context);
+        }
+      }
+    }
+
+    for (context, zero) in other.zero_nodes.iter() {
+      let zero_id = other.node_ids.get(&zero.name);
+
+      let changed : Vec<NodeId> =
+        other.node_seq
+          .iter()
+          .filter(|(changed_context, _)| context.is_empty() || changed_context == &context)
+          .flat_map(|(_, nodes)| nodes.iter())
+          .filter(|(src, seq)| **seq > self.edge_seq && Some(*src) != zero_id)
+          .map(|(src, _)| *src)
+          .collect();
+
+      if !changed.is_empty() {
+        self.zero_dirty.entry(context.clone()).or_default().extend(changed);
+      }
+    }
+
     self.node_count = other.node_count;
     self.node_infos = other.node_infos.clone();
     self.node_ids   = other.node_ids.clone();
     self.contexts   = other.contexts.clone();
+    self.zero_nodes  = other.zero_nodes.clone();
+    self.walk_counts = other.walk_counts.clone();
+    self.recalc_threads = other.recalc_threads;
+    self.centrality     = other.centrality.clone();
+    self.pagerank_seeds = other.pagerank_seeds.clone();
+    self.decay       = other.decay.clone();
+    self.edge_stamps = other.edge_stamps.clone();
+
+    //  History is append-only, so only new changes are copied
+    //  unless the graph was reset.
+    //
+    if other.reset_seq > self.edge_seq {
+      self.edge_history = other.edge_history.clone();
+    } else {
+      for (context, changes) in other.edge_history.iter() {
+        let history = self.edge_history.entry(context.clone()).or_default();
+        if history.len() < changes.len() {
+          history.extend_from_slice(&changes[history.len()..]);
+        }
+      }
+    }
+
+    //  Keep cached ranks not affected by edge changes since the last copy
+    //
+    if other.reset_seq > self.edge_seq {
+      self.ranks_cache.clear();
+    } else {
+      for (context, nodes) in other.node_seq.iter() {
+        for (src, seq) in nodes.iter() {
+          if *seq > self.edge_seq {
+            invalidate_ranks(&mut self.ranks_cache, context, *src);
+          }
+        }
+      }
+    }
+
+    self.edge_seq  = other.edge_seq;
+    self.reset_seq = other.reset_seq;
+    self.node_seq  = other.node_seq.clone();
   }
 
   pub fn reset(&mut self) {
@@ -221,6 +717,18 @@ impl AugMultiGraph {
     self.node_infos   = Vec::new();
     self.node_ids     = HashMap::new();
     self.contexts     = HashMap::new();
+    self.zero_nodes   = default_zero_nodes();
+    self.zero_dirty   = HashMap::new();
+    self.zero_ranks   = HashMap::new();
+    self.walk_counts  = HashMap::new();
+    self.walk_upgrades = Vec::new();
+    self.ranks_cache  = HashMap::new();
+    self.node_seq     = HashMap::new();
+    self.decay        = default_decay();
+    self.edge_stamps  = HashMap::new();
+    self.edge_history = HashMap::new();
+    self.edge_seq    += 1;
+    self.reset_seq    = self.edge_seq;
   }
 
   pub fn node_exists(&self, node_name : &str) -> bool {
@@ -295,6 +803,22 @@ impl AugMultiGraph {
     }
 
     self.contexts.insert(context.to_string(), graph);
+
+    //  Copy timestamps of user edges and apply the decay of the new context
+    //
+    if !context.is_empty() {
+      let stamps : Vec<((NodeId, NodeId), (Weight, u64))> =
+        self.edge_stamps
+          .get("")
+          .map(|x| x.iter().map(|(k, v)| (*k, *v)).collect())
+          .unwrap_or_default();
+
+      for ((src, dst), (amount, timestamp)) in stamps {
+        if self.is_user_edge(src, dst) {
+          self.set_stamped_edge(context, src, dst, amount, timestamp);
+        }
+      }
+    }
   }
 
   pub fn graph_from(&mut self, context : &str) -> &mut MeritRank {
@@ -311,6 +835,116 @@ impl AugMultiGraph {
     }
   }
 
+  //  Raw edge weight, without the decay.
+  //
+  pub fn raw_edge_weight(&mut self, context : &str, src : NodeId, dst : NodeId) -> Weight {
+    log_trace!("raw_edge_weight: `{}` {} {}", context, src, dst);
+
+    match self.edge_stamps.get(context).and_then(|x| x.get(&(src, dst))) {
+      Some((amount, _)) => *amount,
+      None              => self.edge_weight(context, src, dst),
+    }
+  }
+
+  fn half_life(&self, context : &str, dst : NodeId) -> Option<f64> {
+    if let Some(x) = self.decay.by_context.get(context) {
+      return Some(*x);
+    }
+    let kind = self.node_infos.get(dst)?.kind;
+    self.decay.by_kind.get(&kind).copied()
+  }
+
+  //  Zero node edges are recalculated rather than voted,
+  //  so they don't decay.
+  //
+  fn effective_weight(
+    &self,
+    context   : &str,
+    src       : NodeId,
+    dst       : NodeId,
+    amount    : Weight,
+    timestamp : u64,
+    now       : u64
+  ) -> Weight {
+    if self.zero_nodes.values().any(|zero| self.node_ids.get(&zero.name) == Some(&src)) {
+      return amount;
+    }
+    decayed_weight(amount, self.half_life(context, dst), timestamp, now)
+  }
+
+  fn set_stamped_edge(
+    &mut self,
+    context   : &str,
+    src       : NodeId,
+    dst       : NodeId,
+    amount    : Weight,
+    timestamp : u64
+  ) {
+    log_trace!("set_stamped_edge: `{}` {} {} {} {}", context, src, dst, amount, timestamp);
+
+    let stamps = self.edge_stamps.entry(context.to_string()).or_default();
+
+    if amount == 0.0 {
+      stamps.remove(&(src, dst));
+    } else {
+      stamps.insert((src, dst), (amount, timestamp));
+    }
+
+    self.edge_history.entry(context.to_string()).or_default().push(EdgeChange {
+      src,
+      dst,
+      amount,
+      timestamp,
+    });
+
+    let weight = self.effective_weight(context, src, dst, amount, timestamp, now_seconds());
+
+    self.graph_from(context).set_edge(src, dst, weight);
+  }
+
+  //  Copy of the graph with only the context, with edges as they
+  //  were at the moment. Timestamps may come out of order, so the
+  //  latest change by timestamp wins.
+  //
+  fn snapshot_as_of(&self, context : &str, as_of : u64) -> AugMultiGraph {
+    log_trace!("snapshot_as_of: `{}` {}", context, as_of);
+
+    let mut stamps = EdgeStamps::new();
+
+    if let Some(changes) = self.edge_history.get(context) {
+      for change in changes.iter().filter(|change| change.timestamp <= as_of) {
+        match stamps.get(&(change.src, change.dst)) {
+          Some((_, timestamp)) if *timestamp > change.timestamp => {},
+          _ => { stamps.insert((change.src, change.dst), (change.amount, change.timestamp)); },
+        };
+      }
+    }
+
+    stamps.retain(|_, (amount, _)| *amount != 0.0);
+
+    let mut graph = MeritRank::new(Graph::new());
+
+    for _ in 0..self.node_count {
+      graph.get_new_nodeid();
+    }
+
+    for ((src, dst), (amount, timestamp)) in stamps.iter() {
+      graph.set_edge(*src, *dst, self.effective_weight(context, *src, *dst, *amount, *timestamp, as_of));
+    }
+
+    let mut snapshot = AugMultiGraph::new();
+
+    snapshot.node_count = self.node_count;
+    snapshot.node_infos = self.node_infos.clone();
+    snapshot.node_ids   = self.node_ids.clone();
+    snapshot.zero_nodes = self.zero_nodes.clone();
+    snapshot.decay      = self.decay.clone();
+    snapshot.contexts.insert(context.to_string(), graph);
+    snapshot.edge_stamps.insert(context.to_string(), stamps);
+
+    snapshot
+  }
+
   pub fn edge_weight(&mut self, context : &str, src : NodeId, dst : NodeId) -> Weight {
     log_trace!("edge_weight: `{}` {} {}", context, src, dst);
     self.graph_from(context).graph.edge_weight(src, dst).unwrap_or(None).unwrap_or(0.0)
@@ -335,6 +969,28 @@ impl AugMultiGraph {
     graph.graph.edge_weight(src, dst).unwrap_or(None).unwrap_or(0.0) / pos_sum
   }
 
+  //  Edge weight normalized by the sum of absolute weights of the
+  //  source node edges, as in the signed path cost.
+  //
+  pub fn edge_weight_normalized_signed(&mut self, context : &str, src : NodeId, dst : NodeId) -> Weight {
+    log_trace!("edge_weight_normalized_signed: `{}` {} {}", context, src, dst);
+
+    let graph = self.graph_from(context);
+
+    let abs_sum : Weight = match graph.graph.get_node_data(src) {
+      Some(x) => x.pos_edges.values()
+        .chain(x.neg_edges.values())
+        .map(|w| w.abs())
+        .sum(),
+
+      None => 1.0
+    };
+
+    let abs_sum = if abs_sum > EPSILON { abs_sum } else { 1.0 };
+
+    graph.graph.edge_weight(src, dst).unwrap_or(None).unwrap_or(0.0) / abs_sum
+  }
+
   pub fn all_neighbors(&mut self, context : &str, node : NodeId) -> Vec<(NodeId, Weight)> {
     log_trace!("all_neighbors: `{}` {}", context, node);
 
@@ -394,75 +1050,332 @@ impl AugMultiGraph {
     v
   }
 
+  fn calculate(
+    &mut self,
+    context  : &str,
+    node_id  : NodeId,
+    num_walk : usize
+  ) -> Result<(), MeritRankError> {
+    log_trace!("calculate: `{}` {} {}", context, node_id, num_walk);
+
+    self.graph_from(context).calculate(node_id, num_walk)?;
+    self.walk_counts.entry(context.to_string()).or_default().insert(node_id, num_walk);
+
+    if let Some(egos) = self.ranks_cache.get_mut(context) {
+      egos.remove(&node_id);
+    }
+
+    Ok(())
+  }
+
+  //  Recalculate the node if it was calculated with fewer walks
+  //  than requested.
+  //
+  fn upgrade_walks(
+    &mut self,
+    context  : &str,
+    node_id  : NodeId,
+    num_walk : usize
+  ) {
+    log_trace!("upgrade_walks: `{}` {} {}", context, node_id, num_walk);
+
+    let walk_count = match self.walk_counts.get(context) {
+      Some(counts) => *counts.get(&node_id).unwrap_or(&0),
+      None         => 0,
+    };
+
+    if walk_count > 0 && walk_count < num_walk {
+      log_verbose!("Upgrade node {} in `{}`: {} -> {} walks", node_id, context, walk_count, num_walk);
+
+      if let Err(e) = self.calculate(context, node_id, num_walk) {
+        log_error!("(upgrade_walks) {}", e);
+        return;
+      }
+
+      let name = self.node_info_from_id(node_id).name.clone();
+      self.walk_upgrades.push((context.to_string(), name, num_walk));
+    }
+  }
+
+  //  Takes the walk upgrades done since the last call.
+  //
+  pub fn take_walk_upgrades(&mut self) -> Vec<(String, String, usize)> {
+    std::mem::take(&mut self.walk_upgrades)
+  }
+
   fn get_ranks_or_recalculate(
     &mut self,
     context   : &str,
-    node_id   : NodeId
+    node_id   : NodeId,
+    num_walk  : usize
   ) -> Vec<(NodeId, Weight)> {
     log_trace!("get_ranks_or_recalculate");
 
-    let graph = self.graph_from(context);
+    self.upgrade_walks(context, node_id, num_walk);
 
-    match graph.get_ranks(node_id, None) {
-      Ok(ranks) => ranks,
+    match self.graph_from(context).get_ranks(node_id, None) {
+      Ok(ranks) => return ranks,
       Err(MeritRankError::NodeDoesNotExist) => {
         log_warning!("Node does not exist: {}", node_id);
-        vec![]
-      },
-      _ => {
-        log_warning!("Recalculating node: {}", node_id);
-        match graph.calculate(node_id, *NUM_WALK) {
-          Err(e) => {
-            log_error!("(get_ranks_or_recalculate) {}", e);
-            return vec![];
-          },
-          _ => {},
-        };
-        match graph.get_ranks(node_id, None) {
-          Ok(ranks) => ranks,
-          Err(e) => {
-            log_error!("(get_ranks_or_recalculate) {}", e);
-            vec![]
-          }
-        }
+        return vec![];
       },
+      _ => {},
+    };
+
+    log_warning!("Recalculating node: {}", node_id);
+
+    if let Err(e) = self.calculate(context, node_id, num_walk) {
+      log_error!("(get_ranks_or_recalculate) {}", e);
+      return vec![];
+    }
+
+    match self.graph_from(context).get_ranks(node_id, None) {
+      Ok(ranks) => ranks,
+      Err(e) => {
+        log_error!("(get_ranks_or_recalculate) {}", e);
+        vec![]
+      }
     }
   }
 
-  fn get_score_or_recalculate(
+  //  Takes the cached ranks out of the cache, the caller puts them back
+  //  with `put_ranks_cached`, so paging doesn't clone the rank list.
+  //
+  fn take_ranks_cached(
     &mut self,
-    context   : &str,
-    src_id    : NodeId,
-    dst_id    : NodeId
-  ) -> Weight {
-    log_trace!("get_score_or_recalculate");
+    context  : &str,
+    node_id  : NodeId,
+    num_walk : usize
+  ) -> RanksCache {
+    log_trace!("take_ranks_cached: `{}` {} {}", context, node_id, num_walk);
 
-    let graph = self.graph_from(context);
+    self.upgrade_walks(context, node_id, num_walk);
 
-    match graph.get_node_score(src_id, dst_id) {
-      Ok(score) => score,
-      Err(MeritRankError::NodeDoesNotExist) => {
-        log_warning!("Node does not exist: {}, {}", src_id, dst_id);
-        0.0
-      },
-      _ => {
-        log_warning!("Recalculating node {}", src_id);
-        match graph.calculate(src_id, *NUM_WALK) {
-          Err(e) => {
-            log_error!("(get_score_or_recalculate) {}", e);
-            return 0.0;
-          },
-          _ => {},
-        };
-        match graph.get_node_score(src_id, dst_id) {
-          Ok(score) => score,
-          Err(e) => {
-            log_error!("(get_score_or_recalculate) {}", e);
-            0.0
-          }
-        }
+    if let Some(cached) = self.ranks_cache.get_mut(context).and_then(|egos| egos.remove(&node_id)) {
+      return cached;
+    }
+
+    let mut ranks : Vec<(NodeId, NodeKind, Weight)> =
+      self.get_ranks_or_recalculate(context, node_id, num_walk)
+        .into_iter()
+        .map(|(n, w)| (n, self.node_info_from_id(n).kind, w))
+        .collect();
+
+    ranks.sort_by(|(_, _, a), (_, _, b)| b.abs().total_cmp(&a.abs()));
+
+    let visited = ranks.iter().map(|(n, _, _)| *n).collect();
+
+    RanksCache { ranks, visited }
+  }
+
+  fn put_ranks_cached(
+    &mut self,
+    context : &str,
+    node_id : NodeId,
+    cached  : RanksCache
+  ) {
+    log_trace!("put_ranks_cached: `{}` {}", context, node_id);
+
+    self.ranks_cache.entry(context.to_string()).or_default().insert(node_id, cached);
+  }
+
+  fn get_score_or_recalculate(
+    &mut self,
+    context   : &str,
+    src_id    : NodeId,
+    dst_id    : NodeId,
+    num_walk  : usize
+  ) -> Weight {
+    log_trace!("get_score_or_recalculate");
+
+    self.upgrade_walks(context, src_id, num_walk);
+
+    match self.graph_from(context).get_node_score(src_id, dst_id) {
+      Ok(score) => return score,
+      Err(MeritRankError::NodeDoesNotExist) => {
+        log_warning!("Node does not exist: {}, {}", src_id, dst_id);
+        return 0.0;
       },
+      _ => {},
+    };
+
+    log_warning!("Recalculating node {}", src_id);
+
+    if let Err(e) = self.calculate(context, src_id, num_walk) {
+      log_error!("(get_score_or_recalculate) {}", e);
+      return 0.0;
+    }
+
+    match self.graph_from(context).get_node_score(src_id, dst_id) {
+      Ok(score) => score,
+      Err(e) => {
+        log_error!("(get_score_or_recalculate) {}", e);
+        0.0
+      }
+    }
+  }
+
+  //  Search for the shortest paths using the specified
+  //  edge cost model.
+  //
+  fn shortest_paths(
+    &mut self,
+    context         : &str,
+    src_id          : NodeId,
+    dst_id          : NodeId,
+    path_cost       : PathCost,
+    num_paths       : usize,
+    max_path_length : usize
+  ) -> Result<Vec<Vec<NodeId>>, Status<NodeId>> {
+    log_trace!("shortest_paths: `{}` {} {} {:?} {} {}", context, src_id, dst_id, path_cost, num_paths, max_path_length);
+
+    let scores : HashMap<NodeId, Weight> =
+      if path_cost == PathCost::ScoreWeighted {
+        self.get_ranks_or_recalculate(context, src_id, *NUM_WALK).into_iter().collect()
+      } else {
+        HashMap::new()
+      };
+
+    let paths = {
+      let graph = &self.graph_from(context).graph;
+
+      //  Neighbor lists are built once per node,
+      //  so each neighbor request is O(1).
+      //
+
+      let mut links = HashMap::<NodeId, Vec<Link<NodeId, Weight>>>::new();
+
+      let mut get_neighbor = |node : NodeId, index : usize| -> Option<Link<NodeId, Weight>> {
+        links
+          .entry(node)
+          .or_insert_with(|| path_links(graph, node, path_cost, &scores))
+          .get(index)
+          .cloned()
+      };
+
+      k_shortest_paths(
+        src_id,
+        dst_id,
+        0.0,
+        *ASTAR_MAX_STEPS,
+        num_paths,
+        max_path_length,
+        &mut get_neighbor
+      )?
+    };
+
+    log_trace!("found {} paths", paths.len());
+
+    for (k, node_ids) in paths.iter().enumerate() {
+      for node in node_ids.iter() {
+        log_trace!("path {}: {}", k, self.node_info_from_id(*node).name);
+      }
+    }
+
+    Ok(paths)
+  }
+
+  //  Collapse beacons and comments on the path into
+  //  user-to-user edges with normalized weights. Weights are
+  //  normalized the same way as in the path cost.
+  //
+  fn collapse_path(
+    &mut self,
+    context   : &str,
+    node_ids  : &[NodeId],
+    path_cost : PathCost
+  ) -> Vec<(NodeId, NodeId, Weight)> {
+    log_trace!("collapse_path: `{}` {} {:?}", context, node_ids.len(), path_cost);
+
+    let normalized = |graph : &mut AugMultiGraph, src : NodeId, dst : NodeId| -> Weight {
+      match path_cost {
+        PathCost::Signed => graph.edge_weight_normalized_signed(context, src, dst),
+        _                => graph.edge_weight_normalized(context, src, dst),
+      }
+    };
+
+    let mut edges = Vec::<(NodeId, NodeId, Weight)>::new();
+
+    if node_ids.len() < 2 {
+      return edges;
+    }
+
+    edges.reserve_exact(node_ids.len() - 1);
+
+    for k in 0..node_ids.len()-1 {
+      let a = node_ids[k];
+      let b = node_ids[k + 1];
+
+      let a_kind = self.node_info_from_id(a).kind;
+      let b_kind = self.node_info_from_id(b).kind;
+
+      let a_b_weight = normalized(self, a, b);
+
+      if k + 2 == node_ids.len() {
+        if a_kind == NodeKind::User {
+          edges.push((a, b, a_b_weight));
+        } else {
+          log_trace!("ignore node {}", self.node_info_from_id(a).name);
+        }
+      } else if b_kind != NodeKind::User {
+        log_trace!("ignore node {}", self.node_info_from_id(b).name);
+        let c = node_ids[k + 2];
+        let b_c_weight = normalized(self, b, c);
+        let a_c_weight = a_b_weight * b_c_weight * if a_b_weight < 0.0 && b_c_weight < 0.0 { -1.0 } else { 1.0 };
+        edges.push((a, c, a_c_weight));
+      } else if a_kind == NodeKind::User {
+        edges.push((a, b, a_b_weight));
+      } else {
+        log_trace!("ignore node {}", self.node_info_from_id(a).name);
+      }
+    }
+
+    edges
+  }
+
+  //  Outgoing edges of the node with normalized weights.
+  //  If `collapse` is set, beacons and comments are collapsed
+  //  into user-to-user edges.
+  //
+  fn node_edges(
+    &mut self,
+    context       : &str,
+    src_id        : NodeId,
+    collapse      : bool,
+    positive_only : bool
+  ) -> Vec<(NodeId, Weight)> {
+    log_trace!("node_edges: `{}` {} {} {}", context, src_id, collapse, positive_only);
+
+    let mut v = vec![];
+
+    for (dst_id, src_dst_weight) in self.all_neighbors_normalized(context, src_id) {
+      if positive_only && src_dst_weight <= 0.0 {
+        continue;
+      }
+
+      if !collapse {
+        v.push((dst_id, src_dst_weight));
+        continue;
+      }
+
+      let dst_kind = self.node_info_from_id(dst_id).kind;
+
+      if dst_kind == NodeKind::User {
+        v.push((dst_id, src_dst_weight));
+      } else if dst_kind == NodeKind::Comment || dst_kind == NodeKind::Beacon {
+        for (ngh_id, dst_ngh_weight) in self.all_neighbors_normalized(context, dst_id) {
+          if (positive_only && dst_ngh_weight <= 0.0) || ngh_id == src_id || self.node_info_from_id(ngh_id).kind != NodeKind::User {
+            continue;
+          }
+
+          let src_ngh_weight = src_dst_weight * dst_ngh_weight * if src_dst_weight < 0.0 && dst_ngh_weight < 0.0 { -1.0 } else { 1.0 };
+
+          v.push((ngh_id, src_ngh_weight));
+        }
+      }
     }
+
+    v
   }
 
   pub fn find_or_add_node_by_name(
@@ -509,7 +1422,45 @@ impl AugMultiGraph {
     dst     : NodeId,
     amount  : f64
   ) {
-    log_trace!("set_edge: `{}` `{}` `{}` {}", context, src, dst, amount);
+    self.set_edge_at(context, src, dst, amount, now_seconds());
+  }
+
+  //  The null context edge is an aggregate of edges set in other
+  //  contexts, it takes the timestamp of the latest change.
+  //
+  pub fn set_edge_at(
+    &mut self,
+    context   : &str,
+    src       : NodeId,
+    dst       : NodeId,
+    amount    : f64,
+    timestamp : u64
+  ) {
+    log_trace!("set_edge_at: `{}` `{}` `{}` {} {}", context, src, dst, amount, timestamp);
+
+    //  Mark the node as changed for zero node recalculation in each
+    //  affected context. Zero node edges are excluded from the walks
+    //  of the zero node recalculation, so they don't affect the cached ranks.
+    //
+    let user_edge = self.is_user_edge(src, dst);
+
+    for (zero_context, zero) in self.zero_nodes.iter() {
+      if (user_edge || zero_context.is_empty() || zero_context == context) &&
+         self.node_ids.get(&zero.name) != Some(&src) {
+        self.zero_dirty.entry(zero_context.clone()).or_default().insert(src);
+      }
+    }
+
+    let mut changed_contexts : HashSet<String> =
+      HashSet::from(["".to_string(), context.to_string()]);
+
+    if user_edge {
+      changed_contexts.extend(self.contexts.keys().cloned());
+    }
+
+    for changed_context in changed_contexts.iter() {
+      self.edge_changed(changed_context, src);
+    }
 
     if self.is_user_edge(src, dst) {
       //  Create context if does not exist
@@ -519,39 +1470,54 @@ impl AugMultiGraph {
         self.graph_from(context);
       }
 
-      for (enum_context, graph) in &mut self.contexts {
+      let all_contexts : Vec<String> = self.contexts.keys().cloned().collect();
+
+      for enum_context in all_contexts.iter() {
         log_verbose!("Set user edge in `{}`: {} -> {} for {}", enum_context, src, dst, amount);
-        graph.set_edge(src, dst, amount);
+        self.set_stamped_edge(enum_context, src, dst, amount, timestamp);
       }
     } else if context.is_empty() {
       log_verbose!("Set edge in ``: {} -> {} for {}", src, dst, amount);
-      self.graph_from(context).set_edge(src, dst, amount);
+      self.set_stamped_edge(context, src, dst, amount, timestamp);
     } else {
-      let null_weight = self.edge_weight("",      src, dst);
-      let old_weight  = self.edge_weight(context, src, dst);
+      let null_weight = self.raw_edge_weight("",      src, dst);
+      let old_weight  = self.raw_edge_weight(context, src, dst);
       let delta       = null_weight + amount - old_weight;
 
       log_verbose!("Set edge in ``: {} -> {} for {}", src, dst, delta);
-      self.graph_from("").set_edge(src, dst, delta);
+      self.set_stamped_edge("", src, dst, delta, timestamp);
 
       log_verbose!("Set edge in `{}`: {} -> {} for {}", context, src, dst, amount);
-      self.graph_from(context).set_edge(src, dst, amount);
+      self.set_stamped_edge(context, src, dst, amount, timestamp);
     }
   }
 
-  pub fn recalculate_all(&mut self, num_walk : usize) {
-    log_trace!("recalculate_all: {}", num_walk);
+  //  Record the edge change and drop the cached ranks it affects.
+  //
+  fn edge_changed(&mut self, context : &str, src : NodeId) {
+    log_trace!("edge_changed: `{}` {}", context, src);
 
-    let infos = self.node_infos.clone();
+    self.edge_seq += 1;
+    self.node_seq.entry(context.to_string()).or_default().insert(src, self.edge_seq);
+    invalidate_ranks(&mut self.ranks_cache, context, src);
+  }
 
-    let graph = self.graph_from("");
+  //  Walks are stored in the graph itself, and the meritrank crate
+  //  has no way to merge walks calculated on a copy of the graph,
+  //  so unlike the ranks of the zero node recalculation
+  //  this can't be split across threads.
+  //
+  pub fn recalculate_all(&mut self, context : &str, num_walk : usize) {
+    log_trace!("recalculate_all: `{}` {}", context, num_walk);
+
+    let infos = self.node_infos.clone();
 
     for id in 0..infos.len() {
       if (id % 100) == 90 {
         log_trace!("{}%", (id * 100) / infos.len());
       }
       if infos[id].kind == NodeKind::User {
-        match graph.calculate(id, num_walk) {
+        match self.calculate(context, id, num_walk) {
           Ok(_)  => {},
           Err(e) => log_error!("(recalculate_all) {}", e),
         };
@@ -574,11 +1540,16 @@ pub fn read_version() -> &'static str {
 pub fn write_log_level(log_level : u32) {
   log_info!("CMD write_log_level: {}", log_level);
 
-  ERROR  .store(log_level > 0, Ordering::Relaxed);
-  WARNING.store(log_level > 1, Ordering::Relaxed);
-  INFO   .store(log_level > 2, Ordering::Relaxed);
-  VERBOSE.store(log_level > 3, Ordering::Relaxed);
-  TRACE  .store(log_level > 4, Ordering::Relaxed);
+  set_log_level(log_level);
+}
+
+pub fn write_subsystem_log_level(subsystem : &str, log_level : u32) {
+  log_info!("CMD write_subsystem_log_level: {} {}", subsystem, log_level);
+
+  match subsystem_from_str(subsystem) {
+    Some(x) => set_subsystem_log_level(x, log_level),
+    None    => log_error!("(write_subsystem_log_level) Unknown subsystem `{}`", subsystem),
+  };
 }
 
 impl AugMultiGraph {
@@ -588,7 +1559,17 @@ impl AugMultiGraph {
     ego     : &str,
     target  : &str
   ) -> Vec<(String, String, f64)> {
-    log_info!("CMD read_node_score: `{}` `{}` `{}`", context, ego, target);
+    self.read_node_score_with_walks(context, ego, target, *NUM_WALK)
+  }
+
+  pub fn read_node_score_with_walks(
+    &mut self,
+    context  : &str,
+    ego      : &str,
+    target   : &str,
+    num_walk : usize
+  ) -> Vec<(String, String, f64)> {
+    log_info!("CMD read_node_score: `{}` `{}` `{}` {}", context, ego, target, num_walk);
 
     if !self.contexts.contains_key(context) {
       log_error!("(read_node_score) Context does not exist: `{}`", context);
@@ -607,11 +1588,33 @@ impl AugMultiGraph {
 
     let ego_id    = self.find_or_add_node_by_name(ego);
     let target_id = self.find_or_add_node_by_name(target);
-    let w         = self.get_score_or_recalculate(context, ego_id, target_id);
+    let w         = self.get_score_or_recalculate(context, ego_id, target_id, num_walk);
 
     [(ego.to_string(), target.to_string(), w)].to_vec()
   }
 
+  //  Zero `num_walk` means the default number of walks.
+  //
+  pub fn read_node_score_as_of(
+    &mut self,
+    context  : &str,
+    ego      : &str,
+    target   : &str,
+    num_walk : usize,
+    as_of    : u64
+  ) -> Vec<(String, String, f64)> {
+    log_info!("CMD read_node_score_as_of: `{}` `{}` `{}` {} {}", context, ego, target, num_walk, as_of);
+
+    if !self.contexts.contains_key(context) {
+      log_error!("(read_node_score_as_of) Context does not exist: `{}`", context);
+      return [(ego.to_string(), target.to_string(), 0.0)].to_vec();
+    }
+
+    let num_walk = if num_walk == 0 { *NUM_WALK } else { num_walk };
+
+    self.snapshot_as_of(context, as_of).read_node_score_with_walks(context, ego, target, num_walk)
+  }
+
   pub fn read_scores(
     &mut self,
     context       : &str,
@@ -625,10 +1628,39 @@ impl AugMultiGraph {
     index         : u32,
     count         : u32
   ) -> Vec<(String, String, Weight)> {
-    log_info!("CMD read_scores: `{}` `{}` `{}` {} {} {} {} {} {} {}",
+    self.read_scores_with_walks(
+      context,
+      ego,
+      kind_str,
+      hide_personal,
+      score_lt,
+      score_lte,
+      score_gt,
+      score_gte,
+      index,
+      count,
+      *NUM_WALK
+    )
+  }
+
+  pub fn read_scores_with_walks(
+    &mut self,
+    context       : &str,
+    ego           : &str,
+    kind_str      : &str,
+    hide_personal : bool,
+    score_lt      : f64,
+    score_lte     : bool,
+    score_gt      : f64,
+    score_gte     : bool,
+    index         : u32,
+    count         : u32,
+    num_walk      : usize
+  ) -> Vec<(String, String, Weight)> {
+    log_info!("CMD read_scores: `{}` `{}` `{}` {} {} {} {} {} {} {} {}",
               context, ego, kind_str, hide_personal,
               score_lt, score_lte, score_gt, score_gte,
-              index, count);
+              index, count, num_walk);
 
     let kind = match kind_str {
       ""  => NodeKind::Unknown,
@@ -653,21 +1685,17 @@ impl AugMultiGraph {
 
     let node_id = self.find_or_add_node_by_name(ego);
 
-    let ranks = self.get_ranks_or_recalculate(context, node_id);
+    let cached = self.take_ranks_cached(context, node_id, num_walk);
 
-    let mut im : Vec<(NodeId, Weight)> =
-      ranks
-        .into_iter()
-        .map(|(n, w)| (
-          n,
-          self.node_info_from_id(n).kind,
-          w,
-        ))
+    let index = index as usize;
+    let count = count as usize;
+
+    let im : Vec<(NodeId, Weight)> =
+      cached.ranks
+        .iter()
         .filter(|(_, target_kind, _)| kind == NodeKind::Unknown || kind == *target_kind)
         .filter(|(_, _, score)| score_gt < *score   || (score_gte && score_gt <= *score))
         .filter(|(_, _, score)| *score   < score_lt || (score_lte && score_lt >= *score))
-        .collect::<Vec<(NodeId, NodeKind, Weight)>>()
-        .into_iter()
         .filter(|(target_id, target_kind, _)| {
           if !hide_personal || (*target_kind != NodeKind::Comment && *target_kind != NodeKind::Beacon) {
             return true;
@@ -677,22 +1705,18 @@ impl AugMultiGraph {
             _           => true,
           }
         })
-        .map(|(target_id, _, weight)| (target_id, weight))
+        .skip(index)
+        .take(count.saturating_sub(index))
+        .map(|(target_id, _, weight)| (*target_id, *weight))
         .collect();
 
-    im.sort_by(|(_, a), (_, b)| b.abs().total_cmp(&a.abs()));
-
-    let index = index as usize;
-    let count = count as usize;
+    self.put_ranks_cached(context, node_id, cached);
 
     let mut page : Vec<(String, String, Weight)> = vec![];
-    page.reserve_exact(if count < im.len() { count } else { im.len() });
+    page.reserve_exact(im.len());
 
-    for i in index..count {
-      if i >= im.len() {
-        break;
-      }
-      page.push((ego.to_string(), self.node_info_from_id(im[i].0).name.clone(), im[i].1));
+    for (target_id, weight) in im {
+      page.push((ego.to_string(), self.node_info_from_id(target_id).name.clone(), weight));
     }
 
     page
@@ -710,12 +1734,100 @@ impl AugMultiGraph {
     dst     : &str,
     amount  : f64
   ) {
-    log_info!("CMD write_put_edge: `{}` `{}` `{}` {}", context, src, dst, amount);
+    self.write_put_edge_at(context, src, dst, amount, now_seconds());
+  }
+
+  //  Timestamp is in seconds since the Unix epoch.
+  //
+  pub fn write_put_edge_at(
+    &mut self,
+    context   : &str,
+    src       : &str,
+    dst       : &str,
+    amount    : f64,
+    timestamp : u64
+  ) {
+    log_info!("CMD write_put_edge: `{}` `{}` `{}` {} {}", context, src, dst, amount, timestamp);
 
     let src_id = self.find_or_add_node_by_name(src);
     let dst_id = self.find_or_add_node_by_name(dst);
 
-    self.set_edge(context, src_id, dst_id, amount);
+    self.set_edge_at(context, src_id, dst_id, amount, timestamp);
+  }
+
+  //  Calculate the node with the requested number of walks if it was
+  //  calculated with fewer, to keep walks upgraded on a copy of the graph.
+  //
+  pub fn write_upgrade_walks(
+    &mut self,
+    context  : &str,
+    node     : &str,
+    num_walk : usize
+  ) {
+    log_info!("CMD write_upgrade_walks: `{}` `{}` {}", context, node, num_walk);
+
+    let node_id = match self.node_ids.get(node).copied() {
+      Some(x) => x,
+      None    => {
+        log_error!("(write_upgrade_walks) Node does not exist: `{}`", node);
+        return;
+      },
+    };
+
+    let walk_count = match self.walk_counts.get(context) {
+      Some(counts) => *counts.get(&node_id).unwrap_or(&0),
+      None         => 0,
+    };
+
+    if walk_count >= num_walk {
+      return;
+    }
+
+    if let Err(e) = self.calculate(context, node_id, num_walk) {
+      log_error!("(write_upgrade_walks) {}", e);
+    }
+  }
+
+  //  Recompute effective edge weights of the context
+  //  for the current time.
+  //
+  pub fn write_apply_decay(&mut self, context : &str) {
+    log_info!("CMD write_apply_decay: `{}`", context);
+
+    if !self.contexts.contains_key(context) {
+      log_error!("(write_apply_decay) Context does not exist: `{}`", context);
+      return;
+    }
+
+    let now  = now_seconds();
+    let zero = self.zero_node_id_if_exists(context).map(|(id, _)| id);
+
+    let stamps : Vec<((NodeId, NodeId), (Weight, u64))> =
+      self.edge_stamps
+        .get(context)
+        .map(|x| x.iter().map(|(k, v)| (*k, *v)).collect())
+        .unwrap_or_default();
+
+    let mut count = 0;
+
+    for ((src, dst), (amount, timestamp)) in stamps {
+      let weight = self.effective_weight(context, src, dst, amount, timestamp, now);
+
+      if (weight - self.edge_weight(context, src, dst)).abs() < EPSILON {
+        continue;
+      }
+
+      self.graph_from(context).set_edge(src, dst, weight);
+      self.edge_changed(context, src);
+
+      if zero.is_some() && zero != Some(src) {
+        self.zero_dirty.entry(context.to_string()).or_default().insert(src);
+      }
+
+      count += 1;
+    }
+
+    log_verbose!("Decay changed {} edges in `{}`", count, context);
   }
 
   pub fn write_delete_edge(
@@ -763,8 +1875,41 @@ impl AugMultiGraph {
     index         : u32,
     count         : u32
   ) -> Vec<(String, String, Weight)> {
-    log_info!("CMD read_graph: `{}` `{}` `{}` {} {} {}",
-              context, ego, focus, positive_only, index, count);
+    self.read_graph_paths(context, ego, focus, &GraphQuery {
+      positive_only,
+      path_cost       : GRAPH_PATH_COST.as_str(),
+      num_paths       : *GRAPH_NUM_PATHS,
+      max_path_length : *GRAPH_MAX_PATH_LENGTH,
+      index,
+      count,
+    })
+  }
+
+  pub fn read_graph_paths(
+    &mut self,
+    context : &str,
+    ego     : &str,
+    focus   : &str,
+    query   : &GraphQuery
+  ) -> Vec<(String, String, Weight)> {
+    log_info!("CMD read_graph: `{}` `{}` `{}` {:?}", context, ego, focus, query);
+
+    let GraphQuery {
+      positive_only,
+      path_cost       : path_cost_str,
+      num_paths,
+      max_path_length,
+      index,
+      count,
+    } = *query;
+
+    let path_cost = match path_cost_from_str(path_cost_str) {
+      Some(x) => x,
+      None    => {
+        log_error!("(read_graph) Invalid path cost string: `{}`", path_cost_str);
+        return vec![];
+      },
+    };
 
     if !self.contexts.contains_key(context) {
       log_error!("(read_graph) Context does not exist: `{}`", context);
@@ -802,7 +1947,7 @@ impl AugMultiGraph {
       let dst_kind = self.node_info_from_id(dst_id).kind;
 
       if dst_kind == NodeKind::User {
-        if positive_only && self.get_score_or_recalculate(context, ego_id, dst_id) <= 0.0 {
+        if positive_only && self.get_score_or_recalculate(context, ego_id, dst_id, *NUM_WALK) <= 0.0 {
           continue;
         }
 
@@ -845,150 +1990,60 @@ impl AugMultiGraph {
     if ego_id == focus_id {
       log_trace!("ego is same as focus");
     } else {
-      log_trace!("search shortest path");
-
-      let graph_cloned = self.graph_from(context).graph.clone();
-
-      //  ================================
-      //
-      //    A* search
-      //
-
-      let mut open   : Vec<Node<NodeId, Weight>> = vec![];
-      let mut closed : Vec<Node<NodeId, Weight>> = vec![];
-
-      open  .resize(1024, Node::default());
-      closed.resize(1024, Node::default());
-
-      let mut astar_state = init(&mut open, ego_id, focus_id, 0.0);
-
-      let mut steps    = 0;
-      let mut neighbor = None;
-      let mut status   = Status::PROGRESS;
-
-      //  Do 10000 iterations max
-
-      for _ in 0..10000 {
-        steps += 1;
-
-        status = iteration(&mut open, &mut closed, &mut astar_state, neighbor.clone());
-
-        match status.clone() {
-          Status::NEIGHBOR(request) => {
-            match graph_cloned.get_node_data(request.node) {
-              None       => neighbor = None,
-              Some(data) => {
-                let kv : Vec<_> = data.pos_edges.iter().skip(request.index).take(1).collect();
-
-                if kv.is_empty() {
-                  neighbor = None;
-                } else {
-                  let     n = kv[0].0;
-                  let mut w = *kv[0].1;
-
-                  if data.pos_sum > EPSILON {
-                    w /= data.pos_sum;
-                  }
-
-                  neighbor = Some(Link::<NodeId, Weight> {
-                    neighbor       : *n,
-                    exact_distance : if w.abs() < EPSILON { 1_000_000.0 } else { 1.0 / w },
-                    estimate       : 0.0,
-                  });
-                }
-              },
-            }
-          },
-          Status::OUT_OF_MEMORY => {
-            open  .resize(open  .len() * 2, Node::default());
-            closed.resize(closed.len() * 2, Node::default());
-          },
-          Status::SUCCESS  => break,
-          Status::FAIL     => break,
-          Status::PROGRESS => {},
-        };
-      }
-
-      log_trace!("did {} A* iterations", steps);
+      log_trace!("search shortest paths");
+
+      let paths = match self.shortest_paths(
+        context,
+        ego_id,
+        focus_id,
+        path_cost,
+        std::cmp::max(num_paths, 1) as usize,
+        max_path_length as usize
+      ) {
+        Ok(x)             => x,
+        Err(Status::FAIL) => {
+          log_error!("(read_graph) Path does not exist from {} to {}", ego_id, focus_id);
+          return vec![];
+        },
+        Err(_) => {
+          log_error!("(read_graph) Unable to find a path from {} to {}", ego_id, focus_id);
+          return vec![];
+        },
+      };
 
-      if status == Status::SUCCESS {
-        log_trace!("path found");
-      } else if status == Status::FAIL {
-        log_error!("(read_graph) Path does not exist from {} to {}", ego_id, focus_id);
-        return vec![];
-      } else {
-        log_error!("(read_graph) Unable to find a path from {} to {}", ego_id, focus_id);
+      if paths.is_empty() {
+        log_warning!("(read_graph) No paths shorter than {} from {} to {}", max_path_length, ego_id, focus_id);
         return vec![];
       }
 
-      let mut ego_to_focus : Vec<NodeId> = vec![];
-      ego_to_focus.resize(astar_state.num_closed, 0);
-      let n = path(&closed, &astar_state, &mut ego_to_focus);
-      ego_to_focus.resize(n, 0);
-
-      for node in ego_to_focus.iter() {
-        log_trace!("path: {}", self.node_info_from_id(*node).name);
-      }
-
-      //  ================================
-
-      let mut edges = Vec::<(NodeId, NodeId, Weight)>::new();
-      edges.reserve_exact(ego_to_focus.len() - 1);
-
-      log_trace!("process shortest path");
+      for ego_to_focus in paths.iter() {
+        let edges = self.collapse_path(context, ego_to_focus, path_cost);
 
-      for k in 0..ego_to_focus.len()-1 {
-        let a = ego_to_focus[k];
-        let b = ego_to_focus[k + 1];
+        log_trace!("add path to the graph");
 
-        let a_kind = self.node_info_from_id(a).kind;
-        let b_kind = self.node_info_from_id(b).kind;
+        for (src, dst, weight) in edges {
+          if !indices.contains_key(&src) {
+            let index = im_graph.add_node(src);
+            indices.insert(src, index);
+            ids.insert(index, src);
+          }
 
-        let a_b_weight = self.edge_weight_normalized(context, a, b);
+          if !indices.contains_key(&dst) {
+            let index = im_graph.add_node(dst);
+            indices.insert(dst, index);
+            ids.insert(index, dst);
+          }
 
-        if k + 2 == ego_to_focus.len() {
-          if a_kind == NodeKind::User {
-            edges.push((a, b, a_b_weight));
+          if let (Some(src_idx), Some(dst_idx)) = (indices.get(&src), indices.get(&dst)) {
+            im_graph.add_edge(*src_idx, *dst_idx, weight);
           } else {
-            log_trace!("ignore node {}", self.node_info_from_id(a).name);
+            log_error!("(read_graph) Got invalid node id");
           }
-        } else if b_kind != NodeKind::User {
-          log_trace!("ignore node {}", self.node_info_from_id(b).name);
-          let c = ego_to_focus[k + 2];
-          let b_c_weight = self.edge_weight_normalized(context, b, c);
-          let a_c_weight = a_b_weight * b_c_weight * if a_b_weight < 0.0 && b_c_weight < 0.0 { -1.0 } else { 1.0 };
-          edges.push((a, c, a_c_weight));
-        } else if a_kind == NodeKind::User {
-          edges.push((a, b, a_b_weight));
-        } else {
-          log_trace!("ignore node {}", self.node_info_from_id(a).name);
         }
       }
+    }
 
-      log_trace!("add path to the graph");
-
-      for (src, dst, weight) in edges {
-        if !indices.contains_key(&src) {
-          let index = im_graph.add_node(src);
-          indices.insert(src, index);
-          ids.insert(index, src);
-        }
-
-        if !indices.contains_key(&dst) {
-          let index = im_graph.add_node(dst);
-          indices.insert(dst, index);
-          ids.insert(index, dst);
-        }
-
-        if let (Some(src_idx), Some(dst_idx)) = (indices.get(&src), indices.get(&dst)) {
-          im_graph.add_edge(*src_idx, *dst_idx, weight);
-        } else {
-          log_error!("(read_graph) Got invalid node id");
-        }
-      }
-    }
-
-    log_trace!("remove self references");
+    log_trace!("remove self references");
 
     for (_, src_index) in indices.iter() {
       let neighbors : Vec<_> =
@@ -1050,6 +2105,217 @@ impl AugMultiGraph {
       .collect()
   }
 
+  pub fn read_explain_score(
+    &mut self,
+    context         : &str,
+    ego             : &str,
+    target          : &str,
+    path_cost_str   : &str,
+    num_paths       : u32,
+    max_path_length : u32
+  ) -> Vec<(u32, String, String, Weight, Weight)> {
+    log_info!("CMD read_explain_score: `{}` `{}` `{}` `{}` {} {}",
+              context, ego, target, path_cost_str, num_paths, max_path_length);
+
+    let path_cost = match path_cost_from_str(path_cost_str) {
+      Some(x) => x,
+      None    => {
+        log_error!("(read_explain_score) Invalid path cost string: `{}`", path_cost_str);
+        return vec![];
+      },
+    };
+
+    if !self.contexts.contains_key(context) {
+      log_error!("(read_explain_score) Context does not exist: `{}`", context);
+      return vec![];
+    }
+
+    if !self.node_exists(ego) {
+      log_error!("(read_explain_score) Node does not exist: `{}`", ego);
+      return vec![];
+    }
+
+    if !self.node_exists(target) {
+      log_error!("(read_explain_score) Node does not exist: `{}`", target);
+      return vec![];
+    }
+
+    let ego_id    = self.find_or_add_node_by_name(ego);
+    let target_id = self.find_or_add_node_by_name(target);
+
+    if ego_id == target_id {
+      log_verbose!("Ego is same as target");
+      return vec![];
+    }
+
+    let paths = match self.shortest_paths(
+      context,
+      ego_id,
+      target_id,
+      path_cost,
+      std::cmp::max(num_paths, 1) as usize,
+      max_path_length as usize
+    ) {
+      Ok(x)             => x,
+      Err(Status::FAIL) => {
+        log_error!("(read_explain_score) Path does not exist from {} to {}", ego_id, target_id);
+        return vec![];
+      },
+      Err(_) => {
+        log_error!("(read_explain_score) Unable to find a path from {} to {}", ego_id, target_id);
+        return vec![];
+      },
+    };
+
+    //  Each chain is returned as a sequence of hops with
+    //  the product of hop weights as the chain contribution.
+    //
+
+    let mut v : Vec<(u32, String, String, Weight, Weight)> = vec![];
+
+    for (k, node_ids) in paths.iter().enumerate() {
+      let edges = self.collapse_path(context, node_ids, path_cost);
+
+      let contribution : Weight =
+        edges
+          .iter()
+          .map(|(_, _, weight)| *weight)
+          .product();
+
+      for (src_id, dst_id, weight) in edges {
+        v.push((
+          k as u32,
+          self.node_info_from_id(src_id).name.clone(),
+          self.node_info_from_id(dst_id).name.clone(),
+          weight,
+          contribution
+        ));
+      }
+    }
+
+    v
+  }
+
+  pub fn read_neighborhood(
+    &mut self,
+    context       : &str,
+    ego           : &str,
+    max_depth     : u32,
+    collapse      : bool,
+    positive_only : bool,
+    max_nodes     : u32,
+    index         : u32,
+    count         : u32
+  ) -> Vec<(String, String, Weight)> {
+    log_info!("CMD read_neighborhood: `{}` `{}` {} {} {} {} {} {}",
+              context, ego, max_depth, collapse, positive_only,
+              max_nodes, index, count);
+
+    if !self.contexts.contains_key(context) {
+      log_error!("(read_neighborhood) Context does not exist: `{}`", context);
+      return vec![];
+    }
+
+    if !self.node_exists(ego) {
+      log_error!("(read_neighborhood) Node does not exist: `{}`", ego);
+      return vec![];
+    }
+
+    let ego_id = self.find_or_add_node_by_name(ego);
+
+    let scores : HashMap<NodeId, Weight> =
+      self.get_ranks_or_recalculate(context, ego_id, *NUM_WALK)
+        .into_iter()
+        .collect();
+
+    //  Nodes the ego has no opinion about are pruned
+    //
+    let passes = |id : NodeId| -> bool {
+      if id == ego_id {
+        return true;
+      }
+      match scores.get(&id) {
+        Some(score) => if positive_only { *score > EPSILON } else { score.abs() > EPSILON },
+        None        => false,
+      }
+    };
+
+    log_trace!("enumerate neighborhood");
+
+    let mut edges  = HashMap::<(NodeId, NodeId), Weight>::new();
+    let mut depths = HashMap::<NodeId, u32>::new();
+    let mut queue  = VecDeque::<NodeId>::new();
+
+    depths.insert(ego_id, 0);
+    queue.push_back(ego_id);
+
+    while let Some(src_id) = queue.pop_front() {
+      let depth = *depths.get(&src_id).unwrap_or(&max_depth);
+
+      if depth >= max_depth {
+        continue;
+      }
+
+      for (dst_id, weight) in self.node_edges(context, src_id, collapse, positive_only) {
+        if dst_id == src_id || !passes(dst_id) {
+          continue;
+        }
+
+        //  Parallel edges through different beacons and comments are summed
+        //
+        *edges.entry((src_id, dst_id)).or_insert(0.0) += weight;
+
+        if let std::collections::hash_map::Entry::Vacant(e) = depths.entry(dst_id) {
+          e.insert(depth + 1);
+          queue.push_back(dst_id);
+        }
+      }
+    }
+
+    log_trace!("prune neighborhood");
+
+    let mut nodes : Vec<(NodeId, Weight)> =
+      depths
+        .keys()
+        .filter(|id| **id != ego_id)
+        .map(|id| (*id, *scores.get(id).unwrap_or(&0.0)))
+        .collect();
+
+    nodes.sort_by(|(_, a), (_, b)| b.abs().total_cmp(&a.abs()));
+
+    let kept : HashSet<NodeId> =
+      std::iter::once(ego_id)
+        .chain(
+          nodes
+            .into_iter()
+            .take((max_nodes as usize).saturating_sub(1))
+            .map(|(id, _)| id)
+        )
+        .collect();
+
+    let mut edge_ids : Vec<(NodeId, NodeId, Weight)> =
+      edges
+        .into_iter()
+        .filter(|((src_id, dst_id), weight)|
+          kept.contains(src_id) && kept.contains(dst_id) && weight.abs() > EPSILON
+        )
+        .map(|((src_id, dst_id), weight)| (src_id, dst_id, weight))
+        .collect();
+
+    edge_ids.sort_by(|(_, _, a), (_, _, b)| b.abs().total_cmp(&a.abs()));
+
+    edge_ids
+      .into_iter()
+      .skip(index as usize)
+      .take(count as usize)
+      .map(|(src_id, dst_id, weight)| {(
+        self.node_info_from_id(src_id).name.clone(),
+        self.node_info_from_id(dst_id).name.clone(),
+        weight
+      )})
+      .collect()
+  }
+
   pub fn read_connected(
     &mut self,
     context   : &str,
@@ -1081,6 +2347,39 @@ impl AugMultiGraph {
     v
   }
 
+  pub fn read_connected_as_of(
+    &mut self,
+    context : &str,
+    ego     : &str,
+    as_of   : u64
+  ) -> Vec<(String, String)> {
+    log_info!("CMD read_connected_as_of: `{}` `{}` {}", context, ego, as_of);
+
+    if !self.contexts.contains_key(context) {
+      log_error!("(read_connected_as_of) Context does not exist: `{}`", context);
+      return vec![];
+    }
+
+    self.snapshot_as_of(context, as_of).read_connected(context, ego)
+  }
+
+  //  `(context, nodes, edges)` sorted by context
+  //
+  pub fn graph_sizes(&self) -> Vec<(String, usize, usize)> {
+    let mut v : Vec<(String, usize, usize)> =
+      self.contexts
+        .iter()
+        .map(|(context, graph)| (
+          context.clone(),
+          graph.graph.nodes.len(),
+          graph.graph.nodes.iter().map(|x| x.pos_edges.len() + x.neg_edges.len()).sum(),
+        ))
+        .collect();
+
+    v.sort_by(|a, b| a.0.cmp(&b.0));
+    v
+  }
+
   pub fn read_node_list(&self) -> Vec<(String,)> {
     log_info!("CMD read_node_list");
 
@@ -1117,12 +2416,32 @@ impl AugMultiGraph {
     v
   }
 
+  pub fn read_edges_as_of(&mut self, context : &str, as_of : u64) -> Vec<(String, String, Weight)> {
+    log_info!("CMD read_edges_as_of: `{}` {}", context, as_of);
+
+    if !self.contexts.contains_key(context) {
+      log_error!("(read_edges_as_of) Context does not exist: `{}`", context);
+      return vec![];
+    }
+
+    self.snapshot_as_of(context, as_of).read_edges(context)
+  }
+
   pub fn read_mutual_scores(
     &mut self,
     context   : &str,
     ego       : &str
   ) -> Vec<(String, Weight, Weight)> {
-    log_info!("CMD read_mutual_scores: `{}` `{}`", context, ego);
+    self.read_mutual_scores_with_walks(context, ego, *NUM_WALK)
+  }
+
+  pub fn read_mutual_scores_with_walks(
+    &mut self,
+    context   : &str,
+    ego       : &str,
+    num_walk  : usize
+  ) -> Vec<(String, Weight, Weight)> {
+    log_info!("CMD read_mutual_scores: `{}` `{}` {}", context, ego, num_walk);
 
     if !self.contexts.contains_key(context) {
       log_error!("(read_mutual_scores) Context does not exist: `{}`", context);
@@ -1135,7 +2454,7 @@ impl AugMultiGraph {
     }
 
     let ego_id = self.find_or_add_node_by_name(ego);
-    let ranks  = self.get_ranks_or_recalculate(context, ego_id);
+    let ranks  = self.get_ranks_or_recalculate(context, ego_id, num_walk);
     let mut v  = Vec::<(String, Weight, Weight)>::new();
 
     v.reserve_exact(ranks.len());
@@ -1147,7 +2466,7 @@ impl AugMultiGraph {
         v.push((
           info.name,
           score,
-          self.get_score_or_recalculate(context, node, ego_id)
+          self.get_score_or_recalculate(context, node, ego_id, num_walk)
         ));
       }
     }
@@ -1173,16 +2492,10 @@ impl AugMultiGraph {
 
     let src_id = self.find_or_add_node_by_name(src);
 
-    let mut v : Vec<u8> = vec![];
-    v.reserve_exact(self.node_infos[src_id].seen_nodes.len() * 8);
-
-    for &x in &self.node_infos[src_id].seen_nodes {
-      for i in 0..8 {
-        v.push((x & (0xff << (8 * i)) >> (8 * i)) as u8);
-      }
+    match &self.node_infos[src_id].seen_nodes {
+      Some(filter) => filter.to_bytes(),
+      None         => vec![],
     }
-
-    return v;
   }
 
   pub fn write_new_edges_filter(
@@ -1192,16 +2505,117 @@ impl AugMultiGraph {
   ) {
     log_info!("CMD write_new_edges_filter: `{}` {:?}", src, filter_bytes);
 
+    let filter = if filter_bytes.is_empty() {
+      None
+    } else {
+      match SeenFilter::from_bytes(filter_bytes) {
+        Ok(x)  => Some(x),
+        Err(e) => {
+          log_error!("(write_new_edges_filter) {}", e);
+          return;
+        },
+      }
+    };
+
+    if let Some(x) = &filter {
+      if x.size_bytes() > *FILTER_MAX_SIZE {
+        log_error!("(write_new_edges_filter) Filter is too large: {} bytes", x.size_bytes());
+        return;
+      }
+    }
+
     let src_id = self.find_or_add_node_by_name(src);
 
-    let mut v : Vec<u64> = vec![];
-    v.resize(((filter_bytes.len() + 7) / 8) * 8, 0);
+    self.node_infos[src_id].seen_nodes = filter;
+  }
+
+  pub fn write_mark_seen(
+    &mut self,
+    src   : &str,
+    nodes : &[&str]
+  ) {
+    log_info!("CMD write_mark_seen: `{}` {:?}", src, nodes);
+
+    let src_id = match self.node_ids.get(src).copied() {
+      Some(x) => x,
+      None    => {
+        log_warning!("(write_mark_seen) Node does not exist: `{}`", src);
+        return;
+      },
+    };
 
-    for i in 0..filter_bytes.len() {
-      v[i / 8] = (filter_bytes[i] as u64) << (8 * (i % 8));
+    for node in nodes {
+      match self.node_ids.get(*node).copied() {
+        Some(dst_id) => self.mark_seen_id(src_id, dst_id),
+        None         => log_warning!("(write_mark_seen) Node does not exist: `{}`", node),
+      }
     }
+  }
+
+  pub fn write_clear_seen(&mut self, src : &str) {
+    log_info!("CMD write_clear_seen: `{}`", src);
 
-    self.node_infos[src_id].seen_nodes = v;
+    match self.node_ids.get(src).copied() {
+      Some(src_id) => self.node_infos[src_id].seen_nodes = None,
+      None         => log_warning!("(write_clear_seen) Node does not exist: `{}`", src),
+    }
+  }
+
+  pub fn read_is_seen(
+    &self,
+    src   : &str,
+    nodes : &[&str]
+  ) -> Vec<(String, bool)> {
+    log_info!("CMD read_is_seen: `{}` {:?}", src, nodes);
+
+    let src_id = self.node_ids.get(src).copied();
+
+    nodes
+      .iter()
+      .map(|node| {
+        let seen = match (src_id, self.node_ids.get(*node)) {
+          (Some(src_id), Some(dst_id)) => self.is_seen_id(src_id, *dst_id),
+          _                            => false,
+        };
+        (node.to_string(), seen)
+      })
+      .collect()
+  }
+
+  //  Per node `(node, kind, count, size in bytes, fill ratio, false positive rate)`
+  //  sorted by node name, and the aggregate over all filters
+  //  `(number of filters, total size in bytes, mean fill ratio,
+  //  mean false positive rate, max false positive rate)`.
+  //
+  pub fn read_seen_filter_stats(&self) -> (Vec<SeenFilterStats>, SeenFilterTotals) {
+    log_info!("CMD read_seen_filter_stats");
+
+    let mut stats : Vec<SeenFilterStats> =
+      self.node_infos
+        .iter()
+        .filter_map(|info| {
+          let filter = info.seen_nodes.as_ref()?;
+          Some((
+            info.name.clone(),
+            seen_filter_kind_to_str(filter.kind()).to_string(),
+            filter.count() as u64,
+            filter.size_bytes() as u64,
+            filter.fill_ratio(),
+            filter.false_positive_rate(),
+          ))
+        })
+        .collect();
+
+    stats.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let num_filters = stats.len() as u64;
+    let total_size  = stats.iter().map(|x| x.3).sum();
+    let n           = std::cmp::max(stats.len(), 1) as f64;
+    let mean_fill   = stats.iter().map(|x| x.4).sum::<f64>() / n;
+    let mean_fp     = stats.iter().map(|x| x.5).sum::<f64>() / n;
+    let max_fp      = stats.iter().map(|x| x.5).fold(0.0, f64::max);
+
+    (stats, (num_filters, total_size, mean_fill, mean_fp, max_fp))
   }
 
   pub fn write_fetch_new_edges(
@@ -1211,107 +2625,156 @@ impl AugMultiGraph {
   ) -> Vec<(String, Weight)> {
     log_info!("CMD write_fetch_new_edges: `{}` `{}`", src, prefix);
 
-    let num_hashes = *FILTER_NUM_HASHES;
-    let max_size   = *FILTER_MAX_SIZE / 8;
-
     let src_id = self.find_or_add_node_by_name(src);
 
-    if self.node_infos[src_id].seen_nodes.is_empty() {
-      self.node_infos[src_id].seen_nodes.resize((*FILTER_MIN_SIZE + 7) / 8, 0);
+    self.fetch_new_edges("", src_id, EPSILON, usize::MAX, |info| info.name.starts_with(prefix))
+  }
 
-      log_verbose!("Create the bloom filter with {} bytes for `{}`", 8 * self.node_infos[src_id].seen_nodes.len(), src);
-    }
+  //  Empty list of kinds means any kind.
+  //
+  pub fn write_fetch_new_edges_by_kind(
+    &mut self,
+    context : &str,
+    src     : &str,
+    kinds   : &[&str]
+  ) -> Vec<(String, Weight)> {
+    self.write_fetch_new_edges_paged(context, src, kinds, EPSILON, u32::MAX)
+  }
 
-    //  Fetch new edges
-    //
+  //  Scores below EPSILON are never fetched.
+  //
+  pub fn write_fetch_new_edges_paged(
+    &mut self,
+    context   : &str,
+    src       : &str,
+    kinds     : &[&str],
+    min_score : f64,
+    max_count : u32
+  ) -> Vec<(String, Weight)> {
+    log_info!("CMD write_fetch_new_edges_paged: `{}` `{}` {:?} {} {}", context, src, kinds, min_score, max_count);
 
-    let mut v : Vec<(String, Weight)> = vec![];
+    if !self.contexts.contains_key(context) {
+      log_error!("(write_fetch_new_edges_paged) Context does not exist: `{}`", context);
+      return vec![];
+    }
 
-    for dst_id in 0..self.node_count {
-      //  FIXME Probably we should use NodeKind here.
-      if !self.node_infos[dst_id].name.starts_with(prefix) {
-        continue;
-      }
+    if !self.node_exists(src) {
+      log_error!("(write_fetch_new_edges_paged) Node does not exist: `{}`", src);
+      return vec![];
+    }
 
-      let score = self.get_score_or_recalculate("", src_id, dst_id);
+    let mut node_kinds : Vec<NodeKind> = vec![];
 
-      if score < EPSILON {
-        continue;
+    for kind_str in kinds {
+      match kind_from_str(kind_str) {
+        Some(kind) => node_kinds.push(kind),
+        None       => {
+          log_error!("(write_fetch_new_edges_paged) Invalid node kind string: `{}`", kind_str);
+          return vec![];
+        },
       }
+    }
 
-      let bits = bloom_filter_bits(self.node_infos[src_id].seen_nodes.len(), num_hashes, dst_id);
+    let src_id = self.find_or_add_node_by_name(src);
 
-      if !bloom_filter_contains(&self.node_infos[src_id].seen_nodes, &bits) {
-        v.push((self.node_infos[dst_id].name.clone(), score));
-      } 
-    }
+    self.fetch_new_edges(
+      context,
+      src_id,
+      f64::max(min_score, EPSILON),
+      max_count as usize,
+      |info| node_kinds.is_empty() || node_kinds.contains(&info.kind)
+    )
+  }
 
-    //  Rebuild the bloom filter
-    //
+  //  Unseen nodes from the ego's ranks, by score. Only the returned
+  //  nodes are marked as seen, so nodes cut off by `max_count`
+  //  are fetched next time.
+  //
+  fn fetch_new_edges<F>(
+    &mut self,
+    context   : &str,
+    src_id    : NodeId,
+    min_score : f64,
+    max_count : usize,
+    accept    : F
+  ) -> Vec<(String, Weight)>
+    where F : Fn(&NodeInfo) -> bool
+  {
+    log_trace!("fetch_new_edges: `{}` {}", context, src_id);
 
-    let mut seen_nodes = vec![];
+    if self.node_infos[src_id].seen_nodes.is_none() {
+      let filter = new_seen_filter();
 
-    seen_nodes.resize(std::cmp::min(self.node_infos[src_id].seen_nodes.len(), max_size), 0);
+      log_verbose!("Create the seen filter with {} bytes for {}", filter.size_bytes(), src_id);
 
-    loop {
-      let mut saturated = false;
+      self.node_infos[src_id].seen_nodes = Some(filter);
+    }
 
-      for x in seen_nodes.iter_mut() {
-        *x = 0;
-      }
+    let cached = self.take_ranks_cached(context, src_id, *NUM_WALK);
 
-      for dst_id in 0..self.node_count {
-        let bits      = bloom_filter_bits(seen_nodes.len(), num_hashes, dst_id);
-        let collision = bloom_filter_contains(&mut seen_nodes, &bits);
+    let mut fetched : Vec<(NodeId, Weight)> =
+      cached.ranks
+        .iter()
+        .filter(|(_, _, score)| *score >= min_score)
+        .filter(|(dst_id, _, _)| accept(&self.node_infos[*dst_id]))
+        .filter(|(dst_id, _, _)| !self.is_seen_id(src_id, *dst_id))
+        .map(|(dst_id, _, score)| (*dst_id, *score))
+        .collect();
 
-        if collision && seen_nodes.len() < max_size {
-          //  Resize the bloom filter if it is saturated
+    self.put_ranks_cached(context, src_id, cached);
 
-          let n = seen_nodes.len() * 2;
-          seen_nodes.resize(n, 0);
+    fetched.sort_by(|(id_a, a), (id_b, b)| b.total_cmp(a).then(id_a.cmp(id_b)));
+    fetched.truncate(max_count);
 
-          log_verbose!("Resize the bloom filter to {} bytes for `{}`", 8 * n, src);
+    for (dst_id, _) in fetched.iter() {
+      self.mark_seen_id(src_id, *dst_id);
+    }
 
-          saturated = true;
-          break;
-        }
+    fetched
+      .into_iter()
+      .map(|(dst_id, score)| (self.node_infos[dst_id].name.clone(), score))
+      .collect()
+  }
 
-        //  FIXME Probably we should use NodeKind here.
-        if self.node_infos[dst_id].name.starts_with(prefix) {
-          let score = self.get_score_or_recalculate("", src_id, dst_id);
+  fn is_seen_id(&self, src_id : NodeId, dst_id : NodeId) -> bool {
+    match &self.node_infos[src_id].seen_nodes {
+      Some(filter) => filter.contains(dst_id),
+      None         => false,
+    }
+  }
 
-          if !(score < EPSILON) {
-            bloom_filter_add(&mut seen_nodes, &bits);
-          }
-        } else {
-          //  RUST!!!
-          let len = self.node_infos[src_id].seen_nodes.len();
+  //  Grow the filter if it is saturated, up to the max size.
+  //
+  fn mark_seen_id(&mut self, src_id : NodeId, dst_id : NodeId) {
+    log_trace!("mark_seen_id: {} {}", src_id, dst_id);
 
-          let already_seen = bloom_filter_contains(
-            &mut self.node_infos[src_id].seen_nodes,
-            &bloom_filter_bits(len, num_hashes, dst_id)
-          );
+    let node_count = self.node_count;
+    let filter     = self.node_infos[src_id].seen_nodes.get_or_insert_with(new_seen_filter);
 
-          if already_seen {
-            bloom_filter_add(&mut seen_nodes, &bits);
-          }
-        }
-      }
+    //  Bitmap grows with the node id instead of being resized
+    //
+    if filter.kind() == SeenFilterKind::Bitmap && (dst_id / 64 + 1) * 8 > *FILTER_MAX_SIZE {
+      log_warning!("Max seen filter size is reached for {}", src_id);
+      return;
+    }
 
-      if !saturated {
-        if seen_nodes.len() >= max_size {
-          log_warning!("Max bloom filer size is reached for `{}`", src);
-        }
+    if filter.insert(dst_id) && !filter.is_saturated() {
+      return;
+    }
 
-        self.node_infos[src_id].seen_nodes = seen_nodes;
-        break;
-      }
+    if filter.size_bytes() * 2 > *FILTER_MAX_SIZE {
+      log_warning!("Max seen filter size is reached for {}", src_id);
+      return;
     }
 
-    //  Return fetched edges
+    //  False positives of the old filter are carried over
+    //  into the grown filter.
     //
+    *filter = filter.grown(node_count);
+
+    log_verbose!("Resize the seen filter to {} bytes for {}", filter.size_bytes(), src_id);
 
-    return v;
+    filter.insert(dst_id);
   }
 }
 
@@ -1322,10 +2785,82 @@ impl AugMultiGraph {
 //  ================================================
 
 impl AugMultiGraph {
-  fn reduced_graph(&mut self) -> Vec<(NodeId, NodeId, Weight)> {
-    log_trace!("reduced_graph");
+  fn zero_node_id_if_exists(&self, context : &str) -> Option<(NodeId, usize)> {
+    let zero = self.zero_nodes.get(context)?;
+    Some((*self.node_ids.get(&zero.name)?, zero.top_nodes_limit))
+  }
 
-    let zero = self.find_or_add_node_by_name(ZERO_NODE.as_str());
+  fn zero_node_id(&mut self, context : &str) -> Option<(NodeId, usize)> {
+    log_trace!("zero_node_id: `{}`", context);
+
+    match self.zero_nodes.get(context).cloned() {
+      Some(zero) => Some((self.find_or_add_node_by_name(&zero.name), zero.top_nodes_limit)),
+      None       => {
+        log_error!("(zero_node_id) No zero node in context: `{}`", context);
+        None
+      },
+    }
+  }
+
+  //  Zero node edges of the null context are set as usual,
+  //  and are copied into other contexts. Zero node edges
+  //  of other contexts are set only in that context.
+  //
+  fn set_zero_edge(
+    &mut self,
+    context : &str,
+    zero    : NodeId,
+    dst     : NodeId,
+    amount  : Weight
+  ) {
+    log_trace!("set_zero_edge: `{}` {} {} {}", context, zero, dst, amount);
+
+    if context.is_empty() {
+      self.set_edge(context, zero, dst, amount);
+    } else {
+      self.set_stamped_edge(context, zero, dst, amount, now_seconds());
+      self.edge_changed(context, zero);
+    }
+  }
+
+  //  Copy of the context graph without walks and without the zero node
+  //  outgoing edges.
+  //
+  fn zero_walk_graph(&mut self, context : &str, zero : NodeId) -> MeritRank {
+    log_trace!("zero_walk_graph: `{}` {}", context, zero);
+
+    let mut walk_graph = MeritRank::new(Graph::new());
+
+    for _ in 0..self.node_count {
+      walk_graph.get_new_nodeid();
+    }
+
+    let all_nodes = self.graph_from(context).graph.nodes.iter().enumerate();
+
+    for (src_id, src) in all_nodes {
+      if src_id == zero {
+        continue;
+      }
+
+      let all_edges =
+                src.pos_edges.iter()
+        .chain( src.neg_edges.iter() );
+
+      for (dst_id, weight) in all_edges {
+        walk_graph.set_edge(src_id, *dst_id, *weight);
+      }
+    }
+
+    walk_graph
+  }
+
+  fn reduced_graph(
+    &mut self,
+    context     : &str,
+    zero        : NodeId,
+    incremental : bool
+  ) -> Vec<(NodeId, NodeId, Weight)> {
+    log_trace!("reduced_graph: `{}` {} {}", context, zero, incremental);
 
     let users : Vec<NodeId> =
       self.node_infos
@@ -1341,31 +2876,69 @@ impl AugMultiGraph {
       return vec![];
     }
 
-    for id in users.iter() {
-      match self.graph_from("").calculate(*id, *NUM_WALK) {
-        Ok(_)  => {},
-        Err(e) => log_error!("(reduced_graph) {}", e),
+    let     dirty      = self.zero_dirty.remove(context).unwrap_or_default();
+    let mut zero_ranks = self.zero_ranks.remove(context).unwrap_or_default();
+
+    //  Recalculate only users with no cached ranks,
+    //  and users whose walks visited a changed node.
+    //
+    let affected : Vec<NodeId> =
+      if incremental {
+        users
+          .iter()
+          .copied()
+          .filter(|id| {
+            dirty.contains(id) ||
+            match zero_ranks.get(id) {
+              Some(ranks) => ranks.iter().any(|(node_id, _)| dirty.contains(node_id)),
+              None        => true,
+            }
+          })
+          .collect()
+      } else {
+        users.clone()
       };
+
+    log_verbose!("Recalculate {} of {} users", affected.len(), users.len());
+
+    if !affected.is_empty() {
+      let mut walk_graph = self.zero_walk_graph(context, zero);
+
+      for (id, ranks) in calculate_ranks_parallel(&mut walk_graph, &affected, *NUM_WALK, self.recalc_threads) {
+        zero_ranks.insert(id, ranks);
+      }
     }
 
+    let kind_of = |infos : &Vec<NodeInfo>, id : NodeId| -> NodeKind {
+      match infos.get(id) {
+        Some(info) => info.kind,
+        None       => NodeKind::Unknown,
+      }
+    };
+
     let edges : Vec<(NodeId, NodeId, Weight)> =
       users.into_iter()
         .map(|id| -> Vec<(NodeId, NodeId, Weight)> {
-          self.get_ranks_or_recalculate("", id)
-            .into_iter()
-            .map(|(node_id, score)| (id, node_id, score))
-            .filter(|(ego_id, node_id, score)| {
-              let kind = self.node_info_from_id(*node_id).kind;
-
-              (kind == NodeKind::User || kind == NodeKind::Beacon) &&
-                *score > 0.0 &&
-                ego_id != node_id
-            })
-            .collect()
+          match zero_ranks.get(&id) {
+            Some(ranks) => ranks
+              .iter()
+              .map(|(node_id, score)| (id, *node_id, *score))
+              .filter(|(ego_id, node_id, score)| {
+                let kind = kind_of(&self.node_infos, *node_id);
+
+                (kind == NodeKind::User || kind == NodeKind::Beacon) &&
+                  *score > 0.0 &&
+                  ego_id != node_id
+              })
+              .collect(),
+            None => vec![],
+          }
         })
         .flatten()
         .collect();
 
+    self.zero_ranks.insert(context.to_string(), zero_ranks);
+
     let result : Vec<(NodeId, NodeId, Weight)> =
       edges
         .into_iter()
@@ -1391,53 +2964,98 @@ impl AugMultiGraph {
     result
   }
 
-  fn delete_from_zero(&mut self) {
-    log_trace!("delete_from_zero");
-
-    let src_id = self.find_or_add_node_by_name(ZERO_NODE.as_str());
+  fn delete_from_zero(&mut self, context : &str, zero : NodeId) {
+    log_trace!("delete_from_zero: `{}` {}", context, zero);
 
-    for (dst_id, _) in self.all_neighbors("", src_id) {
-      self.set_edge("", src_id, dst_id, 0.0);
+    for (dst_id, _) in self.all_neighbors(context, zero) {
+      self.set_zero_edge(context, zero, dst_id, 0.0);
     }
   }
 
-  fn top_nodes(&mut self) -> Vec<(NodeId, f64)> {
-    log_trace!("top_nodes");
+  fn top_nodes(
+    &mut self,
+    context         : &str,
+    zero            : NodeId,
+    top_nodes_limit : usize,
+    incremental     : bool
+  ) -> Vec<(NodeId, f64)> {
+    log_trace!("top_nodes: `{}` {} {} {}", context, zero, top_nodes_limit, incremental);
 
-    let reduced = self.reduced_graph();
+    let reduced = self.reduced_graph(context, zero, incremental);
 
     if reduced.is_empty() {
       log_error!("(top_nodes) Reduced graph is empty");
       return vec![];
     }
 
-    let mut pr   = Pagerank::<NodeId>::new();
-    let     zero = self.find_or_add_node_by_name(ZERO_NODE.as_str());
-
-    reduced
-      .iter()
-      .filter(|(source, target, weight)|
-        *source != zero && *target != zero && !(*weight > -EPSILON && *weight < EPSILON)
-      )
-      .for_each(|(source, target, _weight)| {
-        pr.add_edge(*source, *target);
-      });
-
-    log_verbose!("Calculate page rank");
-    pr.calculate();
-
-    let (nodes, scores): (Vec<NodeId>, Vec<f64>) =
-      pr
-        .nodes()  // already sorted by score
-        .into_iter()
-        .take(*TOP_NODES_LIMIT)
+    let edges : Vec<(NodeId, NodeId, Weight)> =
+      reduced
         .into_iter()
-        .unzip();
+        .filter(|(source, target, weight)|
+          *source != zero && *target != zero && !(*weight > -EPSILON && *weight < EPSILON)
+        )
+        .collect();
 
-    let res = nodes
-      .into_iter()
-      .zip(scores)
-      .collect::<Vec<_>>();
+    let ranks : Vec<(NodeId, f64)> = match self.centrality.as_str() {
+      "simple" => {
+        //  Unweighted PageRank with default parameters
+        //
+        let mut pr = Pagerank::<NodeId>::new();
+
+        edges
+          .iter()
+          .for_each(|(source, target, _weight)| {
+            pr.add_edge(*source, *target);
+          });
+
+        log_verbose!("Calculate page rank");
+        pr.calculate();
+
+        pr
+          .nodes()  // already sorted by score
+          .into_iter()
+          .map(|(node_id, score)| (*node_id, score))
+          .collect()
+      },
+
+      centrality => {
+        let seeds : Vec<NodeId> = match centrality {
+          "pagerank"     => vec![],
+          "personalized" => {
+            let seeds : Vec<NodeId> =
+              self.pagerank_seeds
+                .iter()
+                .filter_map(|name| self.node_ids.get(name).copied())
+                .collect();
+
+            if seeds.is_empty() {
+              log_warning!("No PageRank seeds found, using uniform distribution");
+            }
+
+            seeds
+          },
+          _ => {
+            log_error!("(top_nodes) Unknown centrality: `{}`", centrality);
+            vec![]
+          },
+        };
+
+        let params = PagerankParams {
+          damping        : *PAGERANK_DAMPING,
+          tolerance      : *PAGERANK_TOLERANCE,
+          max_iterations : *PAGERANK_MAX_ITERATIONS,
+        };
+
+        log_verbose!("Calculate weighted page rank");
+        weighted_pagerank(&edges, &seeds, &params)
+      },
+    };
+
+    let res : Vec<(NodeId, f64)> =
+      ranks
+        .into_iter()
+        .take(top_nodes_limit)
+        .collect();
 
     if res.is_empty() {
       log_error!("(top_nodes) No top nodes");
@@ -1446,25 +3064,255 @@ impl AugMultiGraph {
     return res;
   }
 
-  pub fn write_recalculate_zero(&mut self) {
-    log_info!("CMD write_recalculate_zero");
+  fn recalculate_zero_full(&mut self, context : &str) -> Vec<(NodeId, Weight, Weight)> {
+    log_trace!("recalculate_zero_full: `{}`", context);
 
-    self.recalculate_all(0); // FIXME Ad hok PERF hack
-    self.delete_from_zero();
+    let (zero, top_nodes_limit) = match self.zero_node_id(context) {
+      Some(x) => x,
+      None    => return vec![],
+    };
 
-    let nodes = self.top_nodes();
+    let old_edges : HashMap<NodeId, Weight> =
+      self.all_neighbors(context, zero)
+        .into_iter()
+        .collect();
 
-    self.recalculate_all(0); // FIXME Ad hok PERF hack
-    {
-      let zero = self.find_or_add_node_by_name(ZERO_NODE.as_str());
+    self.recalculate_all(context, 0); // FIXME Ad hok PERF hack
+    self.delete_from_zero(context, zero);
 
+    //  Walks for the top nodes are calculated on a copy of the graph,
+    //  so the walks cleared above don't need to be cleared again.
+    //
+    let nodes = self.top_nodes(context, zero, top_nodes_limit, false);
+
+    {
       for (k, (node_id, amount)) in nodes.iter().enumerate() {
         if (k % 100) == 90 {
           log_trace!("{}%", (k * 100) / nodes.len());
         }
-        self.set_edge("", zero, *node_id, *amount);
+        self.set_zero_edge(context, zero, *node_id, *amount);
+      }
+    }
+    self.recalculate_all(context, *NUM_WALK); // FIXME Ad hok PERF hack
+
+    zero_edges_diff(&old_edges, &nodes.into_iter().collect())
+  }
+
+  fn set_zero_edges(
+    &mut self,
+    context : &str,
+    zero    : NodeId,
+    nodes   : HashMap<NodeId, Weight>
+  ) -> Vec<(NodeId, Weight, Weight)> {
+    log_trace!("set_zero_edges: `{}` {}", context, zero);
+
+    let old_edges : HashMap<NodeId, Weight> =
+      self.all_neighbors(context, zero)
+        .into_iter()
+        .collect();
+
+    //  Update zero node edges by delta
+    //
+
+    let diff = zero_edges_diff(&old_edges, &nodes);
+
+    for (dst_id, _, amount) in diff.iter() {
+      self.set_zero_edge(context, zero, *dst_id, *amount);
+    }
+
+    log_verbose!("Changed {} zero node edges", diff.len());
+
+    if !diff.is_empty() {
+      match self.calculate(context, zero, *NUM_WALK) {
+        Ok(_)  => {},
+        Err(e) => log_error!("(set_zero_edges) {}", e),
+      };
+    }
+
+    diff
+  }
+
+  fn recalculate_zero_incremental(&mut self, context : &str) -> Vec<(NodeId, Weight, Weight)> {
+    log_trace!("recalculate_zero_incremental: `{}`", context);
+
+    let (zero, top_nodes_limit) = match self.zero_node_id(context) {
+      Some(x) => x,
+      None    => return vec![],
+    };
+
+    let nodes : HashMap<NodeId, Weight> =
+      self.top_nodes(context, zero, top_nodes_limit, true)
+        .into_iter()
+        .collect();
+
+    self.set_zero_edges(context, zero, nodes)
+  }
+
+  fn recalculate_zero(&mut self, context : &str, incremental : bool) -> Vec<(NodeId, Weight, Weight)> {
+    if incremental {
+      self.recalculate_zero_incremental(context)
+    } else {
+      self.recalculate_zero_full(context)
+    }
+  }
+
+  //  Log the zero node edges diff and resolve node names.
+  //
+  fn zero_report(
+    &mut self,
+    context : &str,
+    diff    : Vec<(NodeId, Weight, Weight)>
+  ) -> Vec<(String, Weight, Weight)> {
+    log_trace!("zero_report: `{}`", context);
+
+    let added   = diff.iter().filter(|(_, old_amount, _)| *old_amount == 0.0).count();
+    let removed = diff.iter().filter(|(_, _, new_amount)| *new_amount == 0.0).count();
+
+    log_info!(
+      "Zero node edges in `{}`: {} added, {} removed, {} changed",
+      context, added, removed, diff.len() - added - removed
+    );
+
+    diff
+      .into_iter()
+      .map(|(node_id, old_amount, new_amount)| {
+        let name = self.node_info_from_id(node_id).name.clone();
+        log_verbose!("Zero node edge in `{}`: `{}` {} -> {}", context, name, old_amount, new_amount);
+        (name, old_amount, new_amount)
+      })
+      .collect()
+  }
+
+  //  Calculate new zero node edges without changing the graph.
+  //  Used by the background recalculation on a snapshot copy,
+  //  only users affected by edge changes since the previous
+  //  calculation on the same snapshot are recalculated.
+  //
+  pub fn calculate_zero_edges(&mut self, context : &str) -> Vec<(String, Weight)> {
+    log_trace!("calculate_zero_edges: `{}`", context);
+
+    let (zero, top_nodes_limit) = match self.zero_node_id(context) {
+      Some(x) => x,
+      None    => return vec![],
+    };
+
+    let begin = SystemTime::now();
+    let nodes = self.top_nodes(context, zero, top_nodes_limit, true);
+
+    observe_recalc(context, begin.elapsed().map(|d| d.as_secs_f64()).unwrap_or(0.0));
+
+    nodes
+      .into_iter()
+      .map(|(node_id, amount)| (self.node_info_from_id(node_id).name.clone(), amount))
+      .collect()
+  }
+
+  pub fn write_set_zero_edges(&mut self, context : &str, edges : &[(String, Weight)]) {
+    log_info!("CMD write_set_zero_edges: `{}` {} edges", context, edges.len());
+
+    let zero = match self.zero_node_id(context) {
+      Some((x, _)) => x,
+      None         => return,
+    };
+
+    let mut nodes = HashMap::<NodeId, Weight>::new();
+
+    for (name, amount) in edges {
+      if !self.node_exists(name) {
+        log_warning!("Node does not exist: `{}`", name);
+        continue;
       }
+      let id = self.find_or_add_node_by_name(name);
+      nodes.insert(id, *amount);
     }
-    self.recalculate_all(*NUM_WALK); // FIXME Ad hok PERF hack
+
+    let diff = self.set_zero_edges(context, zero, nodes);
+    self.zero_report(context, diff);
+  }
+
+  pub fn write_set_zero_node(
+    &mut self,
+    context         : &str,
+    node            : &str,
+    top_nodes_limit : usize
+  ) {
+    log_info!("CMD write_set_zero_node: `{}` `{}` {}", context, node, top_nodes_limit);
+
+    if kind_from_name(node) != NodeKind::User {
+      log_error!("(write_set_zero_node) Zero node should be a user: `{}`", node);
+      return;
+    }
+
+    if let Some((zero, _)) = self.zero_node_id_if_exists(context) {
+      if self.node_info_from_id(zero).name != node {
+        self.delete_from_zero(context, zero);
+      }
+    }
+
+    self.create_context_if_does_not_exist(context);
+    self.find_or_add_node_by_name(node);
+
+    self.zero_nodes.insert(context.to_string(), ZeroNode {
+      name : node.to_string(),
+      top_nodes_limit,
+    });
+
+    self.zero_dirty.remove(context);
+    self.zero_ranks.remove(context);
+  }
+
+  pub fn read_zero_nodes(&self) -> Vec<(String, String, u32)> {
+    log_info!("CMD read_zero_nodes");
+
+    let mut v : Vec<(String, String, u32)> =
+      self.zero_nodes
+        .iter()
+        .map(|(context, zero)| (context.clone(), zero.name.clone(), zero.top_nodes_limit as u32))
+        .collect();
+
+    v.sort();
+    v
+  }
+
+  pub fn write_recalculate_zero(&mut self, context : &str) {
+    self.write_recalculate_zero_with_mode(context, *ZERO_INCREMENTAL);
+  }
+
+  pub fn write_recalculate_zero_with_mode(&mut self, context : &str, incremental : bool) {
+    self.write_recalculate_zero_report(context, incremental, false);
+  }
+
+  //  Returns changes of the zero node edges as `(node, old weight, new weight)`.
+  //  In dry run mode, the changes are calculated on a copy of the graph
+  //  the same way, and not applied.
+  //
+  pub fn write_recalculate_zero_report(
+    &mut self,
+    context     : &str,
+    incremental : bool,
+    dry_run     : bool
+  ) -> Vec<(String, Weight, Weight)> {
+    log_info!("CMD write_recalculate_zero: `{}` {} {}", context, incremental, dry_run);
+
+    let begin = SystemTime::now();
+
+    let diff = if dry_run {
+      //  Run the same recalculation on a copy of the graph,
+      //  including the cached ranks of the incremental mode.
+      //
+      let mut scratch = AugMultiGraph::new();
+
+      scratch.copy_from(self);
+      scratch.zero_dirty = self.zero_dirty.clone();
+      scratch.zero_ranks = self.zero_ranks.clone();
+
+      scratch.recalculate_zero(context, incremental)
+    } else {
+      self.recalculate_zero(context, incremental)
+    };
+
+    observe_recalc(context, begin.elapsed().map(|d| d.as_secs_f64()).unwrap_or(0.0));
+
+    self.zero_report(context, diff)
   }
 }
@@ -0,0 +1,232 @@
+//  ================================================================
+//
+//    pagerank.rs
+//
+//  Weighted and personalized PageRank
+//  by power iteration
+//
+//  ================================================================
+
+use std::{
+  hash::Hash,
+  collections::HashMap,
+};
+
+#[derive(Debug, Clone)]
+pub struct PagerankParams {
+  pub damping        : f64,
+  pub tolerance      : f64,
+  pub max_iterations : usize,
+}
+
+impl Default for PagerankParams {
+  fn default() -> PagerankParams {
+    PagerankParams {
+      damping        : 0.85,
+      tolerance      : 1e-6,
+      max_iterations : 100,
+    }
+  }
+}
+
+//  Returns nodes sorted by score, scores sum to 1.
+//
+//  Outgoing edge weights are normalized per node, non-positive
+//  weights are ignored. If `seeds` is empty, the teleport
+//  distribution is uniform over all nodes, otherwise it is
+//  uniform over the seeds.
+//
+pub fn weighted_pagerank<Id>(
+  edges  : &[(Id, Id, f64)],
+  seeds  : &[Id],
+  params : &PagerankParams,
+) -> Vec<(Id, f64)>
+  where Id : Copy + Hash + Eq + Ord
+{
+  //  Enumerate nodes
+  //
+
+  let mut ids   : Vec<Id>              = vec![];
+  let mut index : HashMap<Id, usize>   = HashMap::new();
+
+  let mut index_of = |id : Id| -> usize {
+    match index.get(&id) {
+      Some(n) => *n,
+      None    => {
+        ids.push(id);
+        index.insert(id, ids.len() - 1);
+        ids.len() - 1
+      },
+    }
+  };
+
+  let links : Vec<(usize, usize, f64)> =
+    edges
+      .iter()
+      .filter(|(_, _, weight)| *weight > 0.0)
+      .map(|(src, dst, weight)| (index_of(*src), index_of(*dst), *weight))
+      .collect();
+
+  let teleport_ids : Vec<usize> =
+    seeds
+      .iter()
+      .filter_map(|id| index.get(id).copied())
+      .collect();
+
+  let n = ids.len();
+
+  if n == 0 {
+    return vec![];
+  }
+
+  let mut out_sum : Vec<f64> = vec![0.0; n];
+
+  for (src, _, weight) in links.iter() {
+    out_sum[*src] += weight;
+  }
+
+  let mut teleport : Vec<f64> = vec![0.0; n];
+
+  if teleport_ids.is_empty() {
+    teleport.iter_mut().for_each(|x| *x = 1.0 / n as f64);
+  } else {
+    for id in teleport_ids.iter() {
+      teleport[*id] += 1.0 / teleport_ids.len() as f64;
+    }
+  }
+
+  //  Power iteration
+  //
+
+  let damping   = params.damping.clamp(0.0, 1.0);
+  let mut ranks = teleport.clone();
+  let mut next  : Vec<f64> = vec![0.0; n];
+
+  for _ in 0..params.max_iterations {
+    let dangling : f64 =
+      (0..n)
+        .filter(|i| out_sum[*i] <= 0.0)
+        .map(|i| ranks[i])
+        .sum();
+
+    for i in 0..n {
+      next[i] = (1.0 - damping + damping * dangling) * teleport[i];
+    }
+
+    for (src, dst, weight) in links.iter() {
+      next[*dst] += damping * ranks[*src] * weight / out_sum[*src];
+    }
+
+    let delta : f64 =
+      ranks
+        .iter()
+        .zip(next.iter())
+        .map(|(a, b)| (a - b).abs())
+        .sum();
+
+    std::mem::swap(&mut ranks, &mut next);
+
+    if delta < params.tolerance {
+      break;
+    }
+  }
+
+  let mut res : Vec<(Id, f64)> =
+    ids
+      .into_iter()
+      .zip(ranks)
+      .collect();
+
+  res.sort_by(|(id_a, a), (id_b, b)| b.total_cmp(a).then(id_a.cmp(id_b)));
+
+  res
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn score(ranks : &[(usize, f64)], id : usize) -> f64 {
+    ranks.iter().find(|(n, _)| *n == id).map(|(_, s)| *s).unwrap_or(0.0)
+  }
+
+  #[test]
+  fn empty() {
+    let ranks = weighted_pagerank::<usize>(&[], &[], &PagerankParams::default());
+    assert!(ranks.is_empty());
+  }
+
+  #[test]
+  fn cycle_is_uniform() {
+    let edges = vec![(0, 1, 1.0), (1, 2, 1.0), (2, 0, 1.0)];
+
+    let ranks = weighted_pagerank(&edges, &[], &PagerankParams::default());
+
+    assert_eq!(ranks.len(), 3);
+    for (_, s) in ranks.iter() {
+      assert!((s - 1.0 / 3.0).abs() < 1e-4);
+    }
+  }
+
+  #[test]
+  fn sum_is_one() {
+    let edges = vec![(0, 1, 1.0), (0, 2, 3.0), (1, 2, 1.0), (2, 3, 2.0)];
+
+    let ranks = weighted_pagerank(&edges, &[], &PagerankParams::default());
+    let sum : f64 = ranks.iter().map(|(_, s)| s).sum();
+
+    assert!((sum - 1.0).abs() < 1e-4);
+  }
+
+  #[test]
+  fn weights_matter() {
+    let edges = vec![(0, 1, 1.0), (0, 2, 9.0), (1, 0, 1.0), (2, 0, 1.0)];
+
+    let ranks = weighted_pagerank(&edges, &[], &PagerankParams::default());
+
+    assert!(score(&ranks, 2) > score(&ranks, 1) * 2.0);
+    assert_eq!(ranks[0].0, 0);
+    assert_eq!(ranks[1].0, 2);
+  }
+
+  #[test]
+  fn sorted() {
+    let edges = vec![(0, 1, 1.0), (1, 2, 1.0), (2, 3, 1.0), (3, 1, 1.0), (4, 1, 1.0)];
+
+    let ranks = weighted_pagerank(&edges, &[], &PagerankParams::default());
+
+    for n in 1..ranks.len() {
+      assert!(ranks[n - 1].1 >= ranks[n].1);
+    }
+  }
+
+  #[test]
+  fn personalized() {
+    let edges = vec![
+      (0, 1, 1.0), (1, 0, 1.0),
+      (2, 3, 1.0), (3, 2, 1.0),
+    ];
+
+    let ranks = weighted_pagerank(&edges, &[0], &PagerankParams::default());
+
+    assert!(score(&ranks, 0) > 0.4);
+    assert!(score(&ranks, 1) > 0.4);
+    assert!(score(&ranks, 2) < 1e-6);
+    assert!(score(&ranks, 3) < 1e-6);
+  }
+
+  #[test]
+  fn iteration_cap() {
+    let edges = vec![(0, 1, 1.0)];
+
+    let params = PagerankParams {
+      max_iterations : 0,
+      ..PagerankParams::default()
+    };
+
+    let ranks = weighted_pagerank(&edges, &[], &params);
+
+    assert!((score(&ranks, 0) - 0.5).abs() < 1e-9);
+    assert!((score(&ranks, 1) - 0.5).abs() < 1e-9);
+  }
+}
@@ -1,4 +1,3 @@
-use std::sync::atomic::Ordering;
 use crate::log_error;
 // use crate::log_warning;
 // use crate::log_info;
@@ -12,11 +11,15 @@ pub const CMD_VERSION                : &str = "version";
 pub const CMD_LOG_LEVEL              : &str = "log_level";
 pub const CMD_SYNC                   : &str = "sync";
 pub const CMD_RESET                  : &str = "reset";
-pub const CMD_RECALCULATE_ZERO       : &str = "recalculate_zero";
 pub const CMD_NODE_LIST              : &str = "node_list";
 pub const CMD_READ_NEW_EDGES_FILTER  : &str = "read_new_edges_filter";
 pub const CMD_WRITE_NEW_EDGES_FILTER : &str = "write_new_edges_filter";
-pub const CMD_FETCH_NEW_EDGES        : &str = "fetch_new_edges";
+pub const CMD_ZERO_NODES             : &str = "zero_nodes";
+pub const CMD_MARK_SEEN              : &str = "mark_seen";
+pub const CMD_CLEAR_SEEN             : &str = "clear_seen";
+pub const CMD_IS_SEEN                : &str = "is_seen";
+pub const CMD_SEEN_FILTER_STATS      : &str = "seen_filter_stats";
+pub const CMD_METRICS                : &str = "metrics";
 
 //  With context
 pub const CMD_NODE_SCORE       : &str = "node_score";
@@ -29,35 +32,105 @@ pub const CMD_CONNECTED        : &str = "connected";
 pub const CMD_EDGES            : &str = "edges";
 pub const CMD_MUTUAL_SCORES    : &str = "mutual_scores";
 pub const CMD_CREATE_CONTEXT   : &str = "create_context";
+pub const CMD_EXPLAIN_SCORE    : &str = "explain_score";
+pub const CMD_NEIGHBORHOOD     : &str = "neighborhood";
+pub const CMD_RECALCULATE_ZERO : &str = "recalculate_zero";
+pub const CMD_SET_ZERO_EDGES   : &str = "set_zero_edges";
+pub const CMD_SET_ZERO_NODE    : &str = "set_zero_node";
+pub const CMD_APPLY_DECAY      : &str = "apply_decay";
+pub const CMD_FETCH_NEW_EDGES  : &str = "fetch_new_edges";
+pub const CMD_UPGRADE_WALKS    : &str = "upgrade_walks";
+
+//  All known commands
+pub const ALL_COMMANDS : &[&str] = &[
+  CMD_VERSION,
+  CMD_LOG_LEVEL,
+  CMD_SYNC,
+  CMD_RESET,
+  CMD_NODE_LIST,
+  CMD_READ_NEW_EDGES_FILTER,
+  CMD_WRITE_NEW_EDGES_FILTER,
+  CMD_ZERO_NODES,
+  CMD_MARK_SEEN,
+  CMD_CLEAR_SEEN,
+  CMD_IS_SEEN,
+  CMD_SEEN_FILTER_STATS,
+  CMD_METRICS,
+  CMD_NODE_SCORE,
+  CMD_SCORES,
+  CMD_PUT_EDGE,
+  CMD_DELETE_EDGE,
+  CMD_DELETE_NODE,
+  CMD_GRAPH,
+  CMD_CONNECTED,
+  CMD_EDGES,
+  CMD_MUTUAL_SCORES,
+  CMD_CREATE_CONTEXT,
+  CMD_EXPLAIN_SCORE,
+  CMD_NEIGHBORHOOD,
+  CMD_RECALCULATE_ZERO,
+  CMD_SET_ZERO_EDGES,
+  CMD_SET_ZERO_NODE,
+  CMD_APPLY_DECAY,
+  CMD_FETCH_NEW_EDGES,
+  CMD_UPGRADE_WALKS,
+];
 
 #[derive(Clone)]
 pub struct Command {
-  pub id       : String,
-  pub context  : String,
-  pub blocking : bool,
-  pub payload  : Vec<u8>,
+  pub id         : String,
+  pub context    : String,
+  pub blocking   : bool,
+  pub payload    : Vec<u8>,
+  pub request_id : String,
 }
 
+//  The request id is optional, the request is encoded
+//  without it if the id is empty.
+//
 pub fn encode_request(command : &Command) -> Result<Vec<u8>, String> {
-  match rmp_serde::to_vec(&(
-    command.id.clone(),
-    command.context.clone(),
-    command.blocking,
-    command.payload.clone()
-  )) {
+  let res = if command.request_id.is_empty() {
+    rmp_serde::to_vec(&(
+      command.id.clone(),
+      command.context.clone(),
+      command.blocking,
+      command.payload.clone()
+    ))
+  } else {
+    rmp_serde::to_vec(&(
+      command.id.clone(),
+      command.context.clone(),
+      command.blocking,
+      command.payload.clone(),
+      command.request_id.clone()
+    ))
+  };
+
+  match res {
     Ok(x)  => Ok(x),
     Err(s) => Err(s.to_string()),
   }
 }
 
 pub fn decode_request(request : &[u8]) -> Result<Command, ()> {
+  if let Ok((command_value, context_value, blocking_value, payload_value, request_id_value)) = rmp_serde::from_slice(request) {
+    return Ok(Command {
+      id         : command_value,
+      context    : context_value,
+      blocking   : blocking_value,
+      payload    : payload_value,
+      request_id : request_id_value,
+    });
+  }
+
   match rmp_serde::from_slice(request) {
     Ok((command_value, context_value, blocking_value, payload_value)) => {
       Ok(Command {
-        id       : command_value,
-        context  : context_value,
-        blocking : blocking_value,
-        payload  : payload_value,
+        id         : command_value,
+        context    : context_value,
+        blocking   : blocking_value,
+        payload    : payload_value,
+        request_id : String::new(),
       })
     },
     Err(e) => {
@@ -0,0 +1,1006 @@
+//  ================================================================
+//
+//    seen.rs
+//
+//  Sets of node ids marked as seen by a node,
+//  with stable hashing and a portable encoding
+//
+//  ================================================================
+
+pub trait SeenSet {
+  fn contains(&self, id : usize) -> bool;
+
+  //  Returns false if the set is full and has to be grown.
+  fn insert(&mut self, id : usize) -> bool;
+
+  //  Returns false if the id is not in the set,
+  //  or the set doesn't support removal.
+  fn remove(&mut self, id : usize) -> bool;
+
+  fn clear(&mut self);
+
+  //  Number of inserted ids.
+  fn count(&self) -> usize;
+
+  fn size_bytes(&self) -> usize;
+
+  //  Whether the set should be grown to keep
+  //  false positives low.
+  fn is_saturated(&self) -> bool;
+
+  //  Share of the capacity in use, from 0 to 1.
+  fn fill_ratio(&self) -> f64;
+
+  //  Estimated probability that an id not in the set is reported as seen.
+  fn false_positive_rate(&self) -> f64;
+
+  //  Copy with twice the capacity. Approximate sets can't list
+  //  their ids, so the ids are taken from `0..universe`. Ids that
+  //  were false positives are inserted too, and stay seen in the
+  //  grown set.
+  fn grown(&self, universe : usize) -> Self where Self : Sized;
+
+  fn encode(&self, v : &mut Vec<u8>);
+}
+
+//  Stable across platforms and Rust versions, unlike `DefaultHasher`.
+//
+fn mix(x : u64) -> u64 {
+  let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+  z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+  z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+  z ^ (z >> 31)
+}
+
+//  ================================================================
+//
+//    Encoding
+//
+//  ================================================================
+
+//  Encoded filter layout, all integers are little-endian:
+//
+//    u8        version
+//    u8        kind
+//    ...       kind-specific fields
+//
+pub const SEEN_FILTER_VERSION : u8 = 2;
+
+pub const SEEN_FILTER_BLOOM_HEADER_SIZE : usize = 14;
+
+//  More hashes than bits in a word don't make sense for any filter size
+//  we accept, and would make every lookup arbitrarily slow.
+//
+pub const BLOOM_FILTER_MAX_HASHES : usize = 64;
+
+struct Reader<'a> {
+  bytes : &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+  fn take(&mut self, n : usize) -> Result<&'a [u8], String> {
+    if self.bytes.len() < n {
+      return Err("Filter is too short".to_string());
+    }
+    let (head, tail) = self.bytes.split_at(n);
+    self.bytes = tail;
+    Ok(head)
+  }
+
+  fn u8(&mut self) -> Result<u8, String> {
+    Ok(self.take(1)?[0])
+  }
+
+  fn u16(&mut self) -> Result<u16, String> {
+    let x = self.take(2)?;
+    Ok(u16::from_le_bytes([x[0], x[1]]))
+  }
+
+  fn u32(&mut self) -> Result<usize, String> {
+    let x = self.take(4)?;
+    Ok(u32::from_le_bytes([x[0], x[1], x[2], x[3]]) as usize)
+  }
+
+  fn u64(&mut self) -> Result<u64, String> {
+    let x = self.take(8)?;
+    Ok(u64::from_le_bytes([x[0], x[1], x[2], x[3], x[4], x[5], x[6], x[7]]))
+  }
+
+  fn words(&mut self, n : usize) -> Result<Vec<u64>, String> {
+    if self.bytes.len() / 8 < n {
+      return Err(format!("Filter size mismatch: {} words expected, {} bytes left", n, self.bytes.len()));
+    }
+    (0..n).map(|_| self.u64()).collect()
+  }
+
+  fn finish(&self) -> Result<(), String> {
+    if !self.bytes.is_empty() {
+      return Err(format!("Filter has {} trailing bytes", self.bytes.len()));
+    }
+    Ok(())
+  }
+}
+
+//  Version 1 filters were always bloom filters, and are still accepted
+//  so clients don't lose what they've seen on upgrade:
+//
+//    u8        version
+//    u32       number of hashes
+//    u32       number of words
+//    u64 * n   words
+//
+pub const BLOOM_FILTER_VERSION     : u8    = 1;
+pub const BLOOM_FILTER_HEADER_SIZE : usize = 9;
+
+pub fn bloom_filter_encode(
+  num_hashes : usize,
+  words      : &[u64]
+) -> Vec<u8> {
+  let mut v : Vec<u8> = vec![];
+  v.reserve_exact(BLOOM_FILTER_HEADER_SIZE + words.len() * 8);
+
+  v.push(BLOOM_FILTER_VERSION);
+  put_u32(&mut v, num_hashes);
+  put_u32(&mut v, words.len());
+
+  for x in words {
+    v.extend_from_slice(&x.to_le_bytes());
+  }
+
+  v
+}
+
+//  Returns the number of hashes and the words.
+//
+pub fn bloom_filter_decode(
+  bytes : &[u8]
+) -> Result<(usize, Vec<u64>), String> {
+  let mut r = Reader { bytes };
+
+  let version = r.u8()?;
+
+  if version != BLOOM_FILTER_VERSION {
+    return Err(format!("Unsupported filter version: {}", version));
+  }
+
+  let num_hashes = r.u32()?;
+  let num_words  = r.u32()?;
+  let words      = r.words(num_words)?;
+
+  r.finish()?;
+
+  if num_hashes == 0 {
+    return Err("Filter has no hashes".to_string());
+  }
+
+  if num_hashes > BLOOM_FILTER_MAX_HASHES {
+    return Err(format!("Filter has too many hashes: {}", num_hashes));
+  }
+
+  Ok((num_hashes, words))
+}
+
+fn put_u32(v : &mut Vec<u8>, x : usize) {
+  v.extend_from_slice(&(x as u32).to_le_bytes());
+}
+
+fn put_words(v : &mut Vec<u8>, words : &[u64]) {
+  put_u32(v, words.len());
+  for x in words {
+    v.extend_from_slice(&x.to_le_bytes());
+  }
+}
+
+//  ================================================================
+//
+//    Bloom filter
+//
+//  ================================================================
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct BloomFilter {
+  pub num_hashes : usize,
+  pub count      : usize,
+  pub words      : Vec<u64>,
+}
+
+impl BloomFilter {
+  pub fn new(num_words : usize, num_hashes : usize) -> BloomFilter {
+    BloomFilter {
+      num_hashes : num_hashes.clamp(1, BLOOM_FILTER_MAX_HASHES),
+      count      : 0,
+      words      : vec![0; std::cmp::max(num_words, 1)],
+    }
+  }
+
+  fn bits(&self, id : usize) -> impl Iterator<Item = usize> {
+    let num_bits = (self.words.len() * 64) as u64;
+    let h1       = mix(id as u64);
+    let h2       = mix(h1) | 1;
+
+    (0..self.num_hashes as u64)
+      .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits) as usize)
+  }
+
+  //  Version 1 filters don't store the number of items,
+  //  so it's estimated from the number of bits set.
+  //
+  fn from_words(num_hashes : usize, words : Vec<u64>) -> Result<BloomFilter, String> {
+    if words.is_empty() {
+      return Err("Filter has no words".to_string());
+    }
+
+    let num_bits = (words.len() * 64) as f64;
+    let ones     = words.iter().map(|x| x.count_ones() as f64).sum::<f64>();
+    let count    = if ones < num_bits {
+      (-(num_bits / num_hashes as f64) * (1.0 - ones / num_bits).ln()).round() as usize
+    } else {
+      words.len() * 64
+    };
+
+    Ok(BloomFilter {
+      num_hashes,
+      count : std::cmp::min(count, words.len() * 64),
+      words,
+    })
+  }
+
+  fn decode(r : &mut Reader) -> Result<BloomFilter, String> {
+    let num_hashes = r.u32()?;
+    let count      = r.u32()?;
+    let num_words  = r.u32()?;
+    let words      = r.words(num_words)?;
+
+    if num_hashes == 0 {
+      return Err("Filter has no hashes".to_string());
+    }
+
+    if num_hashes > BLOOM_FILTER_MAX_HASHES {
+      return Err(format!("Filter has too many hashes: {}", num_hashes));
+    }
+
+    if words.is_empty() {
+      return Err("Filter has no words".to_string());
+    }
+
+    if count > words.len() * 64 {
+      return Err(format!("Filter count {} exceeds {} bits", count, words.len() * 64));
+    }
+
+    Ok(BloomFilter { num_hashes, count, words })
+  }
+}
+
+impl SeenSet for BloomFilter {
+  fn contains(&self, id : usize) -> bool {
+    if self.words.is_empty() {
+      return false;
+    }
+    self.bits(id).all(|n| self.words[n / 64] & (1u64 << (n % 64)) != 0)
+  }
+
+  fn insert(&mut self, id : usize) -> bool {
+    if self.words.is_empty() {
+      return false;
+    }
+    if self.contains(id) {
+      return true;
+    }
+    let bits : Vec<usize> = self.bits(id).collect();
+    for n in bits {
+      self.words[n / 64] |= 1u64 << (n % 64);
+    }
+    self.count += 1;
+    true
+  }
+
+  fn remove(&mut self, _id : usize) -> bool {
+    false
+  }
+
+  fn clear(&mut self) {
+    self.words.iter_mut().for_each(|x| *x = 0);
+    self.count = 0;
+  }
+
+  fn count(&self) -> usize {
+    self.count
+  }
+
+  fn size_bytes(&self) -> usize {
+    self.words.len() * 8
+  }
+
+  //  Optimal load is `ln 2` bits per hash.
+  //
+  fn is_saturated(&self) -> bool {
+    (self.count * self.num_hashes) as f64 > (self.words.len() * 64) as f64 * std::f64::consts::LN_2
+  }
+
+  fn fill_ratio(&self) -> f64 {
+    let ones : u32 = self.words.iter().map(|x| x.count_ones()).sum();
+    ones as f64 / std::cmp::max(self.words.len() * 64, 1) as f64
+  }
+
+  fn false_positive_rate(&self) -> f64 {
+    self.fill_ratio().powi(self.num_hashes as i32)
+  }
+
+  fn grown(&self, universe : usize) -> BloomFilter {
+    let mut res = BloomFilter::new(self.words.len() * 2, self.num_hashes);
+    for id in (0..universe).filter(|id| self.contains(*id)) {
+      res.insert(id);
+    }
+    res
+  }
+
+  fn encode(&self, v : &mut Vec<u8>) {
+    put_u32(v, self.num_hashes);
+    put_u32(v, self.count);
+    put_words(v, &self.words);
+  }
+}
+
+//  ================================================================
+//
+//    Cuckoo filter
+//
+//  ================================================================
+
+pub const CUCKOO_BUCKET_SIZE : usize = 4;
+pub const CUCKOO_MAX_KICKS   : usize = 500;
+
+//  Buckets of 16-bit fingerprints, zero is an empty slot.
+//  An entry that didn't fit after `CUCKOO_MAX_KICKS` is kept
+//  aside until the filter is grown.
+//
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct CuckooFilter {
+  pub count    : usize,
+  pub buckets  : Vec<[u16; CUCKOO_BUCKET_SIZE]>,
+  pub overflow : Option<(usize, u16)>,
+}
+
+impl CuckooFilter {
+  pub fn new(num_buckets : usize) -> CuckooFilter {
+    CuckooFilter {
+      count    : 0,
+      buckets  : vec![[0; CUCKOO_BUCKET_SIZE]; std::cmp::max(num_buckets, 1).next_power_of_two()],
+      overflow : None,
+    }
+  }
+
+  fn mask(&self) -> usize {
+    self.buckets.len() - 1
+  }
+
+  fn fingerprint_and_index(&self, id : usize) -> (u16, usize) {
+    let h  = mix(id as u64);
+    let fp = std::cmp::max((h >> 48) as u16, 1);
+    (fp, (h as usize) & self.mask())
+  }
+
+  fn alt_index(&self, index : usize, fp : u16) -> usize {
+    (index ^ mix(fp as u64) as usize) & self.mask()
+  }
+
+  fn has(&self, index : usize, fp : u16) -> bool {
+    self.buckets[index].contains(&fp) || self.overflow == Some((index, fp))
+  }
+
+  fn put(&mut self, index : usize, fp : u16) -> bool {
+    match self.buckets[index].iter_mut().find(|x| **x == 0) {
+      Some(slot) => { *slot = fp; true },
+      None       => false,
+    }
+  }
+
+  fn take(&mut self, index : usize, fp : u16) -> bool {
+    if self.overflow == Some((index, fp)) {
+      self.overflow = None;
+      return true;
+    }
+    match self.buckets[index].iter_mut().find(|x| **x == fp) {
+      Some(slot) => { *slot = 0; true },
+      None       => false,
+    }
+  }
+
+  fn decode(r : &mut Reader) -> Result<CuckooFilter, String> {
+    let count       = r.u32()?;
+    let num_buckets = r.u32()?;
+
+    if num_buckets == 0 || !num_buckets.is_power_of_two() {
+      return Err(format!("Invalid number of buckets: {}", num_buckets));
+    }
+
+    if r.bytes.len() / (2 * CUCKOO_BUCKET_SIZE) < num_buckets {
+      return Err(format!("Filter size mismatch: {} buckets expected, {} bytes left", num_buckets, r.bytes.len()));
+    }
+
+    let mut buckets = vec![[0; CUCKOO_BUCKET_SIZE]; num_buckets];
+
+    for bucket in buckets.iter_mut() {
+      for slot in bucket.iter_mut() {
+        *slot = r.u16()?;
+      }
+    }
+
+    let overflow = match r.u8()? {
+      0 => None,
+      _ => {
+        let index = r.u32()?;
+        let fp    = r.u16()?;
+        if index >= num_buckets || fp == 0 {
+          return Err("Invalid overflow entry".to_string());
+        }
+        Some((index, fp))
+      },
+    };
+
+    Ok(CuckooFilter { count, buckets, overflow })
+  }
+}
+
+impl SeenSet for CuckooFilter {
+  fn contains(&self, id : usize) -> bool {
+    if self.buckets.is_empty() {
+      return false;
+    }
+    let (fp, i1) = self.fingerprint_and_index(id);
+    self.has(i1, fp) || self.has(self.alt_index(i1, fp), fp)
+  }
+
+  fn insert(&mut self, id : usize) -> bool {
+    if self.buckets.is_empty() || self.overflow.is_some() {
+      return false;
+    }
+    if self.contains(id) {
+      return true;
+    }
+
+    let (mut fp, i1) = self.fingerprint_and_index(id);
+    let i2           = self.alt_index(i1, fp);
+
+    self.count += 1;
+
+    if self.put(i1, fp) || self.put(i2, fp) {
+      return true;
+    }
+
+    let mut index = i2;
+
+    for kick in 0..CUCKOO_MAX_KICKS {
+      let slot = kick % CUCKOO_BUCKET_SIZE;
+      std::mem::swap(&mut fp, &mut self.buckets[index][slot]);
+      index = self.alt_index(index, fp);
+      if self.put(index, fp) {
+        return true;
+      }
+    }
+
+    self.overflow = Some((index, fp));
+    false
+  }
+
+  fn remove(&mut self, id : usize) -> bool {
+    if self.buckets.is_empty() {
+      return false;
+    }
+    let (fp, i1) = self.fingerprint_and_index(id);
+    let i2       = self.alt_index(i1, fp);
+    if self.take(i1, fp) || self.take(i2, fp) {
+      self.count -= 1;
+      return true;
+    }
+    false
+  }
+
+  fn clear(&mut self) {
+    self.buckets.iter_mut().for_each(|x| *x = [0; CUCKOO_BUCKET_SIZE]);
+    self.overflow = None;
+    self.count    = 0;
+  }
+
+  fn count(&self) -> usize {
+    self.count
+  }
+
+  fn size_bytes(&self) -> usize {
+    self.buckets.len() * CUCKOO_BUCKET_SIZE * 2
+  }
+
+  fn is_saturated(&self) -> bool {
+    self.overflow.is_some() || self.count * 20 > self.buckets.len() * CUCKOO_BUCKET_SIZE * 19
+  }
+
+  fn fill_ratio(&self) -> f64 {
+    f64::min(1.0, self.count as f64 / std::cmp::max(self.buckets.len() * CUCKOO_BUCKET_SIZE, 1) as f64)
+  }
+
+  //  Each lookup compares the fingerprint with up to two buckets
+  //  of slots, out of 65535 non-zero fingerprints.
+  //
+  fn false_positive_rate(&self) -> f64 {
+    f64::min(1.0, (2 * CUCKOO_BUCKET_SIZE) as f64 * self.fill_ratio() / 65535.0)
+  }
+
+  fn grown(&self, universe : usize) -> CuckooFilter {
+    let mut res = CuckooFilter::new(self.buckets.len() * 2);
+    for id in (0..universe).filter(|id| self.contains(*id)) {
+      res.insert(id);
+    }
+    res
+  }
+
+  fn encode(&self, v : &mut Vec<u8>) {
+    put_u32(v, self.count);
+    put_u32(v, self.buckets.len());
+    for bucket in self.buckets.iter() {
+      for slot in bucket.iter() {
+        v.extend_from_slice(&slot.to_le_bytes());
+      }
+    }
+    match self.overflow {
+      None              => v.push(0),
+      Some((index, fp)) => {
+        v.push(1);
+        put_u32(v, index);
+        v.extend_from_slice(&fp.to_le_bytes());
+      },
+    }
+  }
+}
+
+//  ================================================================
+//
+//    Bitmap
+//
+//  ================================================================
+
+//  Exact set, one bit per node id, so the size depends
+//  on the largest id.
+//
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Bitmap {
+  pub count : usize,
+  pub words : Vec<u64>,
+}
+
+impl Bitmap {
+  pub fn new() -> Bitmap {
+    Bitmap::default()
+  }
+
+  fn decode(r : &mut Reader) -> Result<Bitmap, String> {
+    let _         = r.u32()?;
+    let num_words = r.u32()?;
+    let words     = r.words(num_words)?;
+    let count     = words.iter().map(|x| x.count_ones() as usize).sum();
+
+    Ok(Bitmap { count, words })
+  }
+}
+
+impl SeenSet for Bitmap {
+  fn contains(&self, id : usize) -> bool {
+    match self.words.get(id / 64) {
+      Some(x) => x & (1u64 << (id % 64)) != 0,
+      None    => false,
+    }
+  }
+
+  fn insert(&mut self, id : usize) -> bool {
+    if self.words.len() <= id / 64 {
+      self.words.resize(id / 64 + 1, 0);
+    }
+    if !self.contains(id) {
+      self.words[id / 64] |= 1u64 << (id % 64);
+      self.count += 1;
+    }
+    true
+  }
+
+  fn remove(&mut self, id : usize) -> bool {
+    if !self.contains(id) {
+      return false;
+    }
+    self.words[id / 64] &= !(1u64 << (id % 64));
+    self.count -= 1;
+    true
+  }
+
+  fn clear(&mut self) {
+    self.words.clear();
+    self.count = 0;
+  }
+
+  fn count(&self) -> usize {
+    self.count
+  }
+
+  fn size_bytes(&self) -> usize {
+    self.words.len() * 8
+  }
+
+  fn is_saturated(&self) -> bool {
+    false
+  }
+
+  fn fill_ratio(&self) -> f64 {
+    self.count as f64 / std::cmp::max(self.words.len() * 64, 1) as f64
+  }
+
+  fn false_positive_rate(&self) -> f64 {
+    0.0
+  }
+
+  fn grown(&self, _universe : usize) -> Bitmap {
+    self.clone()
+  }
+
+  fn encode(&self, v : &mut Vec<u8>) {
+    put_u32(v, self.count);
+    put_words(v, &self.words);
+  }
+}
+
+//  ================================================================
+//
+//    Filter of any kind
+//
+//  ================================================================
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SeenFilterKind {
+  Bloom,
+  Cuckoo,
+  Bitmap,
+}
+
+pub fn seen_filter_kind_to_str(kind : SeenFilterKind) -> &'static str {
+  match kind {
+    SeenFilterKind::Bloom  => "bloom",
+    SeenFilterKind::Cuckoo => "cuckoo",
+    SeenFilterKind::Bitmap => "bitmap",
+  }
+}
+
+pub fn seen_filter_kind_from_str(s : &str) -> Option<SeenFilterKind> {
+  match s {
+    "bloom"  => Some(SeenFilterKind::Bloom),
+    "cuckoo" => Some(SeenFilterKind::Cuckoo),
+    "bitmap" => Some(SeenFilterKind::Bitmap),
+    _        => None,
+  }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum SeenFilter {
+  Bloom(BloomFilter),
+  Cuckoo(CuckooFilter),
+  Bitmap(Bitmap),
+}
+
+impl SeenFilter {
+  pub fn new(
+    kind       : SeenFilterKind,
+    size_bytes : usize,
+    num_hashes : usize
+  ) -> SeenFilter {
+    match kind {
+      SeenFilterKind::Bloom  => SeenFilter::Bloom(BloomFilter::new(size_bytes.div_ceil(8), num_hashes)),
+      SeenFilterKind::Cuckoo => SeenFilter::Cuckoo(CuckooFilter::new(size_bytes / (2 * CUCKOO_BUCKET_SIZE))),
+      SeenFilterKind::Bitmap => SeenFilter::Bitmap(Bitmap::new()),
+    }
+  }
+
+  pub fn kind(&self) -> SeenFilterKind {
+    match self {
+      SeenFilter::Bloom(_)  => SeenFilterKind::Bloom,
+      SeenFilter::Cuckoo(_) => SeenFilterKind::Cuckoo,
+      SeenFilter::Bitmap(_) => SeenFilterKind::Bitmap,
+    }
+  }
+
+  fn inner(&self) -> &dyn SeenSet {
+    match self {
+      SeenFilter::Bloom(x)  => x,
+      SeenFilter::Cuckoo(x) => x,
+      SeenFilter::Bitmap(x) => x,
+    }
+  }
+
+  fn inner_mut(&mut self) -> &mut dyn SeenSet {
+    match self {
+      SeenFilter::Bloom(x)  => x,
+      SeenFilter::Cuckoo(x) => x,
+      SeenFilter::Bitmap(x) => x,
+    }
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut v = vec![SEEN_FILTER_VERSION];
+    v.push(match self.kind() {
+      SeenFilterKind::Bloom  => 0,
+      SeenFilterKind::Cuckoo => 1,
+      SeenFilterKind::Bitmap => 2,
+    });
+    self.encode(&mut v);
+    v
+  }
+
+  pub fn from_bytes(bytes : &[u8]) -> Result<SeenFilter, String> {
+    let mut r = Reader { bytes };
+
+    let version = r.u8()?;
+
+    if version == BLOOM_FILTER_VERSION {
+      let (num_hashes, words) = bloom_filter_decode(bytes)?;
+      return Ok(SeenFilter::Bloom(BloomFilter::from_words(num_hashes, words)?));
+    }
+
+    if version != SEEN_FILTER_VERSION {
+      return Err(format!("Unsupported filter version: {}", version));
+    }
+
+    let filter = match r.u8()? {
+      0 => SeenFilter::Bloom (BloomFilter ::decode(&mut r)?),
+      1 => SeenFilter::Cuckoo(CuckooFilter::decode(&mut r)?),
+      2 => SeenFilter::Bitmap(Bitmap      ::decode(&mut r)?),
+      x => return Err(format!("Unknown filter kind: {}", x)),
+    };
+
+    r.finish()?;
+
+    Ok(filter)
+  }
+}
+
+impl SeenSet for SeenFilter {
+  fn contains(&self, id : usize) -> bool {
+    self.inner().contains(id)
+  }
+
+  fn insert(&mut self, id : usize) -> bool {
+    self.inner_mut().insert(id)
+  }
+
+  fn remove(&mut self, id : usize) -> bool {
+    self.inner_mut().remove(id)
+  }
+
+  fn clear(&mut self) {
+    self.inner_mut().clear()
+  }
+
+  fn count(&self) -> usize {
+    self.inner().count()
+  }
+
+  fn size_bytes(&self) -> usize {
+    self.inner().size_bytes()
+  }
+
+  fn is_saturated(&self) -> bool {
+    self.inner().is_saturated()
+  }
+
+  fn fill_ratio(&self) -> f64 {
+    self.inner().fill_ratio()
+  }
+
+  fn false_positive_rate(&self) -> f64 {
+    self.inner().false_positive_rate()
+  }
+
+  fn grown(&self, universe : usize) -> SeenFilter {
+    match self {
+      SeenFilter::Bloom(x)  => SeenFilter::Bloom (x.grown(universe)),
+      SeenFilter::Cuckoo(x) => SeenFilter::Cuckoo(x.grown(universe)),
+      SeenFilter::Bitmap(x) => SeenFilter::Bitmap(x.grown(universe)),
+    }
+  }
+
+  fn encode(&self, v : &mut Vec<u8>) {
+    self.inner().encode(v)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::Rng;
+
+  const KINDS : [SeenFilterKind; 3] = [
+    SeenFilterKind::Bloom,
+    SeenFilterKind::Cuckoo,
+    SeenFilterKind::Bitmap,
+  ];
+
+  #[test]
+  fn insert_contains() {
+    for kind in KINDS {
+      let mut filter = SeenFilter::new(kind, 1024, 4);
+
+      for id in (0..100).step_by(3) {
+        assert!(filter.insert(id));
+      }
+
+      for id in (0..100).step_by(3) {
+        assert!(filter.contains(id));
+      }
+
+      assert_eq!(filter.count(), 34);
+    }
+  }
+
+  #[test]
+  fn bitmap_is_exact() {
+    let mut filter = SeenFilter::new(SeenFilterKind::Bitmap, 0, 0);
+
+    filter.insert(5);
+    filter.insert(700);
+
+    for id in 0..1000 {
+      assert_eq!(filter.contains(id), id == 5 || id == 700);
+    }
+  }
+
+  #[test]
+  fn remove() {
+    for kind in [SeenFilterKind::Cuckoo, SeenFilterKind::Bitmap] {
+      let mut filter = SeenFilter::new(kind, 1024, 4);
+
+      filter.insert(1);
+      filter.insert(2);
+
+      assert!(filter.remove(1));
+      assert!(!filter.remove(1));
+      assert!(!filter.contains(1));
+      assert!(filter.contains(2));
+      assert_eq!(filter.count(), 1);
+    }
+
+    let mut filter = SeenFilter::new(SeenFilterKind::Bloom, 1024, 4);
+
+    filter.insert(1);
+
+    assert!(!filter.remove(1));
+    assert!(filter.contains(1));
+  }
+
+  #[test]
+  fn saturation_and_growth() {
+    for kind in [SeenFilterKind::Bloom, SeenFilterKind::Cuckoo] {
+      let mut filter = SeenFilter::new(kind, 16, 4);
+      let mut id     = 0;
+
+      while !filter.is_saturated() {
+        filter.insert(id);
+        id += 1;
+      }
+
+      let grown = filter.grown(id);
+
+      assert_eq!(grown.size_bytes(), filter.size_bytes() * 2);
+      assert!(!grown.is_saturated());
+
+      for n in 0..id {
+        assert!(grown.contains(n));
+      }
+    }
+  }
+
+  #[test]
+  fn fill_and_false_positives() {
+    for kind in KINDS {
+      let mut filter = SeenFilter::new(kind, 64, 4);
+
+      assert_eq!(filter.fill_ratio(), 0.0);
+      assert_eq!(filter.false_positive_rate(), 0.0);
+
+      for id in 0..20 {
+        filter.insert(id);
+      }
+
+      assert!(filter.fill_ratio() > 0.0);
+      assert!(filter.fill_ratio() <= 1.0);
+      assert!(filter.false_positive_rate() < 1.0);
+    }
+
+    let mut small = BloomFilter::new(1, 4);
+    let mut large = BloomFilter::new(8, 4);
+
+    for id in 0..20 {
+      small.insert(id);
+      large.insert(id);
+    }
+
+    assert!(small.false_positive_rate() > large.false_positive_rate());
+  }
+
+  #[test]
+  fn stable_hashing() {
+    let mut filter = BloomFilter::new(1, 2);
+
+    filter.insert(42);
+
+    assert_eq!(filter.words, vec![(1u64 << 21) | (1u64 << 26)]);
+  }
+
+  #[test]
+  fn round_trip_random() {
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..100 {
+      for kind in KINDS {
+        let mut filter = SeenFilter::new(kind, rng.gen_range(0..512), rng.gen_range(1..16));
+
+        for _ in 0..rng.gen_range(0..200) {
+          let id = rng.gen_range(0..10000);
+          if !filter.insert(id) {
+            filter = filter.grown(10000);
+          }
+        }
+
+        let bytes = filter.to_bytes();
+
+        assert_eq!(SeenFilter::from_bytes(&bytes), Ok(filter));
+      }
+    }
+  }
+
+  #[test]
+  fn decode_invalid() {
+    let mut filter = SeenFilter::new(SeenFilterKind::Bloom, 32, 4);
+
+    filter.insert(1);
+
+    let mut bytes = filter.to_bytes();
+
+    assert_eq!(bytes.len(), SEEN_FILTER_BLOOM_HEADER_SIZE + 32);
+
+    assert!(SeenFilter::from_bytes(&[]).is_err());
+    assert!(SeenFilter::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(SeenFilter::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+    assert!(SeenFilter::from_bytes(&[0; 32]).is_err());
+
+    bytes[1] = 7;
+    assert!(SeenFilter::from_bytes(&bytes).is_err());
+
+    bytes[1] = 0;
+    bytes[0] = 3;
+    assert!(SeenFilter::from_bytes(&bytes).is_err());
+
+    let empty = BloomFilter { num_hashes : 0, count : 0, words : vec![0] };
+    assert!(SeenFilter::from_bytes(&SeenFilter::Bloom(empty).to_bytes()).is_err());
+
+    let too_many_hashes = BloomFilter { num_hashes : BLOOM_FILTER_MAX_HASHES + 1, count : 0, words : vec![0] };
+    assert!(SeenFilter::from_bytes(&SeenFilter::Bloom(too_many_hashes).to_bytes()).is_err());
+
+    let too_many_items = BloomFilter { num_hashes : 4, count : 65, words : vec![0] };
+    assert!(SeenFilter::from_bytes(&SeenFilter::Bloom(too_many_items).to_bytes()).is_err());
+
+    let mut cuckoo = SeenFilter::new(SeenFilterKind::Cuckoo, 64, 0).to_bytes();
+    cuckoo[6] = 3;
+    assert!(SeenFilter::from_bytes(&cuckoo).is_err());
+
+    assert!(SeenFilter::from_bytes(&bloom_filter_encode(4, &[])).is_err());
+  }
+
+  #[test]
+  fn decode_version_1() {
+    let mut filter = BloomFilter::new(4, 3);
+
+    for id in 0..20 {
+      filter.insert(id);
+    }
+
+    let decoded = SeenFilter::from_bytes(&bloom_filter_encode(3, &filter.words)).unwrap();
+
+    assert_eq!(decoded.kind(), SeenFilterKind::Bloom);
+    assert!((0..20).all(|id| decoded.contains(id)));
+    assert!(decoded.count().abs_diff(20) <= 2);
+  }
+}
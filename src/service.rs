@@ -1,5 +1,5 @@
 use std::{
-  sync::atomic::Ordering,
+  sync::atomic::{AtomicU64, Ordering},
   sync::{Arc, Mutex, Condvar},
   ops::DerefMut,
   env::var,
@@ -15,6 +15,7 @@ use crate::log_trace;
 use crate::log::*;
 use crate::protocol::*;
 use crate::operations::*;
+use crate::metrics;
 use std::time::SystemTime;
 
 pub use meritrank::Weight;
@@ -26,15 +27,44 @@ lazy_static::lazy_static! {
       .and_then(|s| s.parse::<usize>().ok())
       .unwrap_or(1);
 
+  pub static ref ZERO_RECALC_INTERVAL : u64 =
+    var("MERITRANK_ZERO_RECALC_INTERVAL")
+      .ok()
+      .and_then(|s| s.parse::<u64>().ok())
+      .unwrap_or(0);
+
+  //  Decay is applied hourly by default if any half-life is set,
+  //  so untouched edges fade out too.
+  //
+  pub static ref DECAY_INTERVAL : u64 =
+    var("MERITRANK_DECAY_INTERVAL")
+      .ok()
+      .and_then(|s| s.parse::<u64>().ok())
+      .unwrap_or(
+        if DECAY_HALF_LIFE.trim().is_empty() && DECAY_HALF_LIFE_KINDS.trim().is_empty() {
+          0
+        } else {
+          3600
+        }
+      );
+
+  pub static ref SLOW_QUERY_THRESHOLD : u64 =
+    var("MERITRANK_SLOW_QUERY_THRESHOLD")
+      .ok()
+      .and_then(|s| s.parse::<u64>().ok())
+      .unwrap_or(5000);
+
   static ref SERVICE_URL : String =
     var("MERITRANK_SERVICE_URL")
       .unwrap_or("tcp://127.0.0.1:10234".to_string());
 }
 
+static NEXT_REQUEST_ID : AtomicU64 = AtomicU64::new(1);
+
 pub struct Data {
   pub graph_readable : Mutex<AugMultiGraph>,
   pub graph_writable : Mutex<AugMultiGraph>,
-  pub queue_commands : Mutex<Vec<Command>>,
+  pub queue_commands : Mutex<Vec<(Command, SystemTime)>>,
   pub write_sync     : Mutex<()>,
   pub cond_add       : Condvar,
   pub cond_done      : Condvar,
@@ -53,7 +83,13 @@ fn perform_command(
      command.id == CMD_PUT_EDGE               ||
      command.id == CMD_CREATE_CONTEXT         ||
      command.id == CMD_WRITE_NEW_EDGES_FILTER ||
-     command.id == CMD_FETCH_NEW_EDGES
+     command.id == CMD_MARK_SEEN              ||
+     command.id == CMD_CLEAR_SEEN             ||
+     command.id == CMD_FETCH_NEW_EDGES        ||
+     command.id == CMD_SET_ZERO_EDGES         ||
+     command.id == CMD_SET_ZERO_NODE          ||
+     command.id == CMD_APPLY_DECAY            ||
+     command.id == CMD_UPGRADE_WALKS
   {
     let mut res = encode_response(&());
 
@@ -79,7 +115,37 @@ fn perform_command(
       CMD_RECALCULATE_ZERO => {
         if let Ok(()) = rmp_serde::from_slice(command.payload.as_slice()) {
           ok = true;
-          graph.write_recalculate_zero();
+          graph.write_recalculate_zero(command.context.as_str());
+        } else if let Ok(incremental) = rmp_serde::from_slice(command.payload.as_slice()) {
+          ok = true;
+          graph.write_recalculate_zero_with_mode(command.context.as_str(), incremental);
+        } else if let Ok((incremental, dry_run)) = rmp_serde::from_slice(command.payload.as_slice()) {
+          ok = true;
+          res = encode_response(&graph.write_recalculate_zero_report(command.context.as_str(), incremental, dry_run));
+        }
+      },
+      CMD_SET_ZERO_EDGES => {
+        if let Ok(edges) = rmp_serde::from_slice::<Vec<(String, Weight)>>(command.payload.as_slice()) {
+          ok = true;
+          graph.write_set_zero_edges(command.context.as_str(), &edges);
+        }
+      },
+      CMD_SET_ZERO_NODE => {
+        if let Ok((node, top_nodes_limit)) = rmp_serde::from_slice(command.payload.as_slice()) {
+          ok = true;
+          graph.write_set_zero_node(command.context.as_str(), node, top_nodes_limit);
+        }
+      },
+      CMD_APPLY_DECAY => {
+        if let Ok(()) = rmp_serde::from_slice(command.payload.as_slice()) {
+          ok = true;
+          graph.write_apply_decay(command.context.as_str());
+        }
+      },
+      CMD_UPGRADE_WALKS => {
+        if let Ok((node, num_walk)) = rmp_serde::from_slice(command.payload.as_slice()) {
+          ok = true;
+          graph.write_upgrade_walks(command.context.as_str(), node, num_walk);
         }
       },
       CMD_DELETE_EDGE => {
@@ -99,6 +165,10 @@ fn perform_command(
           ok = true;
           graph.write_put_edge(command.context.as_str(), src, dst, amount);
         }
+        if let Ok((src, dst, amount, timestamp)) = rmp_serde::from_slice(command.payload.as_slice()) {
+          ok = true;
+          graph.write_put_edge_at(command.context.as_str(), src, dst, amount, timestamp);
+        }
       },
       CMD_CREATE_CONTEXT => {
         if let Ok(()) = rmp_serde::from_slice(command.payload.as_slice()) {
@@ -113,16 +183,44 @@ fn perform_command(
           graph.write_new_edges_filter(src, &v);
         }
       },
+      CMD_MARK_SEEN => {
+        if let Ok((src, nodes)) = rmp_serde::from_slice::<(&str, Vec<&str>)>(command.payload.as_slice()) {
+          ok = true;
+          graph.write_mark_seen(src, &nodes);
+        }
+      },
+      CMD_CLEAR_SEEN => {
+        if let Ok(src) = rmp_serde::from_slice(command.payload.as_slice()) {
+          ok = true;
+          graph.write_clear_seen(src);
+        }
+      },
       CMD_FETCH_NEW_EDGES => {
         if let Ok((src, prefix)) = rmp_serde::from_slice(command.payload.as_slice()) {
+          if command.context.is_empty() {
+            ok = true;
+            res = encode_response(&graph.write_fetch_new_edges(src, prefix));
+          } else {
+            log_error!("(perform_command) Context should be empty for `{}` by prefix", command.id);
+          }
+        }
+        if let Ok((src, kinds)) = rmp_serde::from_slice::<(&str, Vec<&str>)>(command.payload.as_slice()) {
+          ok = true;
+          res = encode_response(&graph.write_fetch_new_edges_by_kind(command.context.as_str(), src, &kinds));
+        }
+        if let Ok((src, kinds, min_score, max_count)) = rmp_serde::from_slice::<(&str, Vec<&str>, f64, u32)>(command.payload.as_slice()) {
           ok = true;
-          res = encode_response(&graph.write_fetch_new_edges(src, prefix));
+          res = encode_response(&graph.write_fetch_new_edges_paged(command.context.as_str(), src, &kinds, min_score, max_count));
         }
       },
       _ => {
         log_error!("(perform_command) Unexpected command `{}`", command.id);
       },
     };
+    //  Walks upgraded on the writable graph are already kept.
+    //
+    graph.take_walk_upgrades();
+
     match data.graph_readable.lock() {
       Ok(ref mut x) => {
         x.copy_from(graph.deref_mut());
@@ -153,10 +251,33 @@ fn perform_command(
     if let Ok(()) = rmp_serde::from_slice(command.payload.as_slice()) {
       return encode_response(&read_version());
     }
+  } else if command.id == CMD_METRICS {
+    if let Ok(()) = rmp_serde::from_slice(command.payload.as_slice()) {
+      let queue_depth = match data.queue_commands.lock() {
+        Ok(x)  => x.len(),
+        Err(e) => {
+          log_error!("(perform_command) {}", e);
+          0
+        },
+      };
+
+      let graph_sizes = match data.graph_readable.lock() {
+        Ok(x)  => x.graph_sizes(),
+        Err(e) => {
+          log_error!("(perform_command) {}", e);
+          vec![]
+        },
+      };
+
+      return encode_response(&metrics::render(&metrics::snapshot(), queue_depth, &graph_sizes));
+    }
   } else if command.id == CMD_LOG_LEVEL {
     if let Ok(log_level) = rmp_serde::from_slice(command.payload.as_slice()) {
       return encode_response(&write_log_level(log_level));
     }
+    if let Ok((subsystem, log_level)) = rmp_serde::from_slice::<(&str, u32)>(command.payload.as_slice()) {
+      return encode_response(&write_subsystem_log_level(subsystem, log_level));
+    }
   } else {
     //  Read commands
 
@@ -167,58 +288,174 @@ fn perform_command(
         return Err(());
       },
     };
-    match command.id.as_str() {
-      CMD_NODE_LIST => {
-        if let Ok(()) = rmp_serde::from_slice(command.payload.as_slice()) {
-          return encode_response(&graph.read_node_list());
-        }
-      },
-      CMD_NODE_SCORE => {
-        if let Ok((ego, target)) = rmp_serde::from_slice(command.payload.as_slice()) {
-          return encode_response(&graph.read_node_score(command.context.as_str(), ego, target));
-        }
-      },
-      CMD_SCORES => {
-        if let Ok((ego, kind, hide_personal, lt, lte, gt, gte, index, count)) = rmp_serde::from_slice(command.payload.as_slice()) {
-          return encode_response(&graph.read_scores(command.context.as_str(), ego, kind, hide_personal, lt, lte, gt, gte, index, count));
-        }
-      },
-      CMD_GRAPH => {
-        if let Ok((ego, focus, positive_only, index, count)) = rmp_serde::from_slice(command.payload.as_slice()) {
-          return encode_response(&graph.read_graph(command.context.as_str(), ego, focus, positive_only, index, count));
-        }
-      },
-      CMD_CONNECTED => {
-        if let Ok(node) = rmp_serde::from_slice(command.payload.as_slice()) {
-          return encode_response(&graph.read_connected(command.context.as_str(), node));
-        }
-      },
-      CMD_EDGES => {
-        if let Ok(()) = rmp_serde::from_slice(command.payload.as_slice()) {
-          return encode_response(&graph.read_edges(command.context.as_str()));
-        }
-      },
-      CMD_MUTUAL_SCORES => {
-        if let Ok(ego) = rmp_serde::from_slice(command.payload.as_slice()) {
-          return encode_response(&graph.read_mutual_scores(command.context.as_str(), ego));
-        }
-      },
-      CMD_READ_NEW_EDGES_FILTER => {
-        if let Ok(src) = rmp_serde::from_slice(command.payload.as_slice()) {
-          return encode_response(&graph.read_new_edges_filter(src));
-        }
-      },
-      _ => {
-        log_error!("(perform_command) Unknown command: `{}`", command.id);
-        return Err(());
-      }
+    let res = perform_read_command(graph.deref_mut(), &command);
+
+    //  Walks upgraded on the readable graph are lost on the next write,
+    //  so the upgrades are repeated on the writable graph.
+    //
+    let upgrades = graph.take_walk_upgrades();
+    std::mem::drop(graph);
+
+    for (context, node, num_walk) in upgrades {
+      put_walk_upgrade(data, context, node, num_walk);
     }
+
+    return res;
   }
 
   log_error!("(perform_command) Invalid payload for command `{}`: {:?}", command.id.as_str(), command.payload);
   Err(())
 }
 
+fn perform_read_command(
+  graph   : &mut AugMultiGraph,
+  command : &Command
+) -> Result<Vec<u8>, ()> {
+  log_trace!("perform_read_command");
+
+  match command.id.as_str() {
+    CMD_NODE_LIST => {
+      if let Ok(()) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_node_list());
+      }
+    },
+    CMD_NODE_SCORE => {
+      if let Ok((ego, target)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_node_score(command.context.as_str(), ego, target));
+      }
+      if let Ok((ego, target, num_walk)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_node_score_with_walks(command.context.as_str(), ego, target, num_walk));
+      }
+      if let Ok((ego, target, num_walk, as_of)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_node_score_as_of(command.context.as_str(), ego, target, num_walk, as_of));
+      }
+    },
+    CMD_SCORES => {
+      if let Ok((ego, kind, hide_personal, lt, lte, gt, gte, index, count)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_scores(command.context.as_str(), ego, kind, hide_personal, lt, lte, gt, gte, index, count));
+      }
+      if let Ok((ego, kind, hide_personal, lt, lte, gt, gte, index, count, num_walk)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_scores_with_walks(command.context.as_str(), ego, kind, hide_personal, lt, lte, gt, gte, index, count, num_walk));
+      }
+    },
+    CMD_GRAPH => {
+      if let Ok((ego, focus, positive_only, index, count)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_graph(command.context.as_str(), ego, focus, positive_only, index, count));
+      }
+      if let Ok((ego, focus, positive_only, index, count, num_paths, max_path_length)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_graph_paths(command.context.as_str(), ego, focus, &GraphQuery {
+          positive_only,
+          path_cost : GRAPH_PATH_COST.as_str(),
+          num_paths,
+          max_path_length,
+          index,
+          count,
+        }));
+      }
+      if let Ok((ego, focus, positive_only, index, count, num_paths, max_path_length, path_cost)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_graph_paths(command.context.as_str(), ego, focus, &GraphQuery {
+          positive_only,
+          path_cost,
+          num_paths,
+          max_path_length,
+          index,
+          count,
+        }));
+      }
+    },
+    CMD_EXPLAIN_SCORE => {
+      if let Ok((ego, target)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_explain_score(command.context.as_str(), ego, target, GRAPH_PATH_COST.as_str(), *GRAPH_NUM_PATHS, *GRAPH_MAX_PATH_LENGTH));
+      }
+      if let Ok((ego, target, num_paths, max_path_length)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_explain_score(command.context.as_str(), ego, target, GRAPH_PATH_COST.as_str(), num_paths, max_path_length));
+      }
+      if let Ok((ego, target, num_paths, max_path_length, path_cost)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_explain_score(command.context.as_str(), ego, target, path_cost, num_paths, max_path_length));
+      }
+    },
+    CMD_NEIGHBORHOOD => {
+      if let Ok((ego, max_depth, collapse, positive_only, max_nodes, index, count)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_neighborhood(command.context.as_str(), ego, max_depth, collapse, positive_only, max_nodes, index, count));
+      }
+    },
+    CMD_CONNECTED => {
+      if let Ok(node) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_connected(command.context.as_str(), node));
+      }
+      if let Ok((node, as_of)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_connected_as_of(command.context.as_str(), node, as_of));
+      }
+    },
+    CMD_EDGES => {
+      if let Ok(()) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_edges(command.context.as_str()));
+      }
+      if let Ok(as_of) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_edges_as_of(command.context.as_str(), as_of));
+      }
+    },
+    CMD_MUTUAL_SCORES => {
+      if let Ok(ego) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_mutual_scores(command.context.as_str(), ego));
+      }
+      if let Ok((ego, num_walk)) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_mutual_scores_with_walks(command.context.as_str(), ego, num_walk));
+      }
+    },
+    CMD_READ_NEW_EDGES_FILTER => {
+      if let Ok(src) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_new_edges_filter(src));
+      }
+    },
+    CMD_IS_SEEN => {
+      if let Ok((src, nodes)) = rmp_serde::from_slice::<(&str, Vec<&str>)>(command.payload.as_slice()) {
+        return encode_response(&graph.read_is_seen(src, &nodes));
+      }
+    },
+    CMD_SEEN_FILTER_STATS => {
+      if let Ok(()) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_seen_filter_stats());
+      }
+    },
+    CMD_ZERO_NODES => {
+      if let Ok(()) = rmp_serde::from_slice(command.payload.as_slice()) {
+        return encode_response(&graph.read_zero_nodes());
+      }
+    },
+    _ => {
+      log_error!("(perform_read_command) Unknown command: `{}`", command.id);
+      return Err(());
+    }
+  }
+
+  log_error!("(perform_read_command) Invalid payload for command `{}`: {:?}", command.id.as_str(), command.payload);
+  Err(())
+}
+
+fn put_walk_upgrade(
+  data     : &Data,
+  context  : String,
+  node     : String,
+  num_walk : usize
+) {
+  let payload = match rmp_serde::to_vec(&(node, num_walk)) {
+    Ok(x)  => x,
+    Err(e) => {
+      log_error!("(put_walk_upgrade) {}", e);
+      return;
+    },
+  };
+
+  put_for_write(data, Command {
+    id         : CMD_UPGRADE_WALKS.to_string(),
+    context,
+    blocking   : false,
+    payload,
+    request_id : String::new(),
+  });
+}
+
 fn command_queue_thread(data : &Data) {
   let mut queue = data.queue_commands.lock().expect("Mutex lock failed");
   loop {
@@ -230,15 +467,10 @@ fn command_queue_thread(data : &Data) {
     queue.clear();
     std::mem::drop(queue);
 
-    for cmd in commands {
-      let begin    = SystemTime::now();
-      let _        = perform_command(&data, cmd.clone());
-      let duration = SystemTime::now().duration_since(begin).unwrap().as_secs();
-
-      log_trace!("perform_command - done");
-      if duration > 5 {
-        log_warning!("Command was done in {} seconds", duration);
-      }
+    for (cmd, queued) in commands {
+      log_scope_begin(cmd.request_id.as_str(), cmd.id.as_str(), cmd.context.as_str());
+      let _ = perform_and_measure(data, cmd, queued);
+      log_scope_end();
     }
 
     std::mem::drop(write);
@@ -253,25 +485,175 @@ fn command_queue_thread(data : &Data) {
   }
 }
 
-fn put_for_write(
+fn next_request_id() -> String {
+  NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed).to_string()
+}
+
+fn payload_summary(payload : &[u8]) -> String {
+  const MAX_BYTES : usize = 32;
+
+  if payload.len() > MAX_BYTES {
+    format!("{} bytes {:?}...", payload.len(), &payload[..MAX_BYTES])
+  } else {
+    format!("{} bytes {:?}", payload.len(), payload)
+  }
+}
+
+//  Perform the command, update metrics and write
+//  the slow query log if the command took too long.
+//
+fn perform_and_measure(
   data    : &Data,
   command : Command,
+  queued  : SystemTime,
+) -> Result<Vec<u8>, ()> {
+  let id         = command.id.clone();
+  let context    = command.context.clone();
+  let request_id = command.request_id.clone();
+  let summary    = payload_summary(command.payload.as_slice());
+
+  let begin   = SystemTime::now();
+  let res     = perform_command(data, command);
+  let end     = SystemTime::now();
+  let waited  = begin.duration_since(queued).unwrap_or_default();
+  let elapsed = end.duration_since(begin).unwrap_or_default();
+
+  metrics::observe_command(id.as_str(), elapsed.as_secs_f64(), res.is_ok());
+
+  log_trace!("perform_command - done");
+
+  let total = waited + elapsed;
+
+  if *SLOW_QUERY_THRESHOLD > 0 && total.as_millis() >= *SLOW_QUERY_THRESHOLD as u128 {
+    log_warning!(
+      "SLOW request {} `{}` in `{}`: queued {} ms, performed {} ms, total {} ms, payload {}",
+      request_id,
+      id,
+      context,
+      waited.as_millis(),
+      elapsed.as_millis(),
+      total.as_millis(),
+      summary
+    );
+  }
+
+  res
+}
+
+fn put_for_write(
+  data        : &Data,
+  mut command : Command,
 ) {
   log_trace!("put_for_write");
 
+  if command.request_id.is_empty() {
+    command.request_id = next_request_id();
+  }
+
   let mut queue = data.queue_commands.lock().expect("Mutex lock failed");
-  queue.push(command);
+  queue.push((command, SystemTime::now()));
   log_trace!("notify add");
   data.cond_add.notify_one();
 }
 
+//  Recalculate zero node edges on a snapshot of the graph,
+//  then apply them through the write queue. The snapshot is kept
+//  between runs, so cached ranks of unaffected users are reused.
+//
+fn zero_recalc_thread(data : &Data, interval : u64) {
+  let mut snapshot = AugMultiGraph::new();
+
+  loop {
+    std::thread::sleep(std::time::Duration::from_secs(interval));
+
+    log_trace!("zero_recalc_thread (loop)");
+
+    match data.graph_readable.lock() {
+      Ok(ref x) => snapshot.copy_from(x),
+      Err(e)    => {
+        log_error!("(zero_recalc_thread) {}", e);
+        continue;
+      },
+    };
+
+    let contexts : Vec<String> = snapshot.zero_nodes.keys().cloned().collect();
+
+    for context in contexts {
+      let begin    = SystemTime::now();
+      let edges    = snapshot.calculate_zero_edges(context.as_str());
+      let duration = begin.elapsed().map(|d| d.as_secs()).unwrap_or(0);
+
+      log_info!("Zero node recalculation in `{}` was done in {} seconds", context, duration);
+
+      let payload = match rmp_serde::to_vec(&edges) {
+        Ok(x)  => x,
+        Err(e) => {
+          log_error!("(zero_recalc_thread) {}", e);
+          continue;
+        },
+      };
+
+      put_for_write(data, Command {
+        id         : CMD_SET_ZERO_EDGES.to_string(),
+        context,
+        blocking   : false,
+        payload,
+        request_id : String::new(),
+      });
+    }
+  }
+}
+
+//  Periodically apply the edge weight decay in all contexts
+//  through the write queue.
+//
+fn decay_thread(data : &Data, interval : u64) {
+  loop {
+    std::thread::sleep(std::time::Duration::from_secs(interval));
+
+    log_trace!("decay_thread (loop)");
+
+    let contexts : Vec<String> = match data.graph_readable.lock() {
+      Ok(ref x) => x.contexts.keys().cloned().collect(),
+      Err(e)    => {
+        log_error!("(decay_thread) {}", e);
+        continue;
+      },
+    };
+
+    for context in contexts {
+      let payload = match rmp_serde::to_vec(&()) {
+        Ok(x)  => x,
+        Err(e) => {
+          log_error!("(decay_thread) {}", e);
+          continue;
+        },
+      };
+
+      put_for_write(data, Command {
+        id         : CMD_APPLY_DECAY.to_string(),
+        context,
+        blocking   : false,
+        payload,
+        request_id : String::new(),
+      });
+    }
+  }
+}
+
 fn decode_and_handle_request(
   data    : &Data,
   request : &[u8]
 ) -> Result<Vec<u8>, ()> {
   log_trace!("decode_and_handle_request");
 
-  let command = decode_request(request)?;
+  let mut command = decode_request(request)?;
+
+  if command.request_id.is_empty() {
+    command.request_id = next_request_id();
+  }
+
+  log_scope_begin(command.request_id.as_str(), command.id.as_str(), command.context.as_str());
 
   if command.context.is_empty() {
     log_trace!("decoded command `{}` in NULL with payload {:?}", command.id, command.payload);
@@ -283,31 +665,30 @@ fn decode_and_handle_request(
     command.id == CMD_VERSION                ||
     command.id == CMD_LOG_LEVEL              ||
     command.id == CMD_RESET                  ||
-    command.id == CMD_RECALCULATE_ZERO       ||
     command.id == CMD_NODE_LIST              ||
+    command.id == CMD_ZERO_NODES             ||
     command.id == CMD_READ_NEW_EDGES_FILTER  ||
     command.id == CMD_WRITE_NEW_EDGES_FILTER ||
-    command.id == CMD_FETCH_NEW_EDGES
+    command.id == CMD_MARK_SEEN              ||
+    command.id == CMD_CLEAR_SEEN             ||
+    command.id == CMD_IS_SEEN                ||
+    command.id == CMD_SEEN_FILTER_STATS      ||
+    command.id == CMD_METRICS
   ) {
     log_error!("(decode_and_handle_request) Context should be empty");
+    log_scope_end();
     return Err(())
   }
 
-  if !command.blocking {
+  let res = if !command.blocking {
     put_for_write(&data, command);
     encode_response(&())
   } else {
-    let begin    = SystemTime::now();
-    let res      = perform_command(&data, command);
-    let duration = SystemTime::now().duration_since(begin).unwrap().as_secs();
-
-    log_trace!("perform_command - done");
-    if duration > 5 {
-      log_warning!("Command was done in {} seconds", duration);
-    }
+    perform_and_measure(data, command, SystemTime::now())
+  };
 
-    res
-  }
+  log_scope_end();
+  res
 }
 
 fn worker_callback(
@@ -363,13 +744,17 @@ fn worker_callback(
 pub fn main_async(threads : usize) -> Result<(), ()> {
   let threads = if threads < 1 { 1 } else { threads };
 
+  init_log_levels();
+
   log_info!("Starting server {} at {}, {} threads", VERSION, *SERVICE_URL, threads);
   log_info!("NUM_WALK={}", *NUM_WALK);
+  log_info!("ZERO_RECALC_INTERVAL={}", *ZERO_RECALC_INTERVAL);
+  log_info!("DECAY_INTERVAL={}", *DECAY_INTERVAL);
 
   let data = Arc::<Data>::new(Data {
     graph_readable : Mutex::<AugMultiGraph>::new(AugMultiGraph::new()),
     graph_writable : Mutex::<AugMultiGraph>::new(AugMultiGraph::new()),
-    queue_commands : Mutex::<Vec<Command>>::new(vec![]),
+    queue_commands : Mutex::<Vec<(Command, SystemTime)>>::new(vec![]),
     write_sync     : Mutex::<()>::new(()),
     cond_add       : Condvar::new(),
     cond_done      : Condvar::new(),
@@ -381,6 +766,22 @@ pub fn main_async(threads : usize) -> Result<(), ()> {
     command_queue_thread(&data_cloned);
   });
 
+  if *ZERO_RECALC_INTERVAL > 0 {
+    let data_cloned = data.clone();
+
+    std::thread::spawn(move || {
+      zero_recalc_thread(&data_cloned, *ZERO_RECALC_INTERVAL);
+    });
+  }
+
+  if *DECAY_INTERVAL > 0 {
+    let data_cloned = data.clone();
+
+    std::thread::spawn(move || {
+      decay_thread(&data_cloned, *DECAY_INTERVAL);
+    });
+  }
+
   let s = match Socket::new(Protocol::Rep0) {
     Ok(x)  => x,
     Err(e) => {
@@ -1,6 +1,9 @@
 use crate::operations::*;
 use crate::protocol::*;
+use crate::seen::*;
 use std::time::SystemTime;
+use std::collections::HashMap;
+use rand::Rng;
 
 fn put_testing_edges(graph : &mut AugMultiGraph, context : &str) {
   graph.write_put_edge(context, "U0cd6bd2dde4f", "B7f628ad203b5",  1.0);
@@ -1263,7 +1266,7 @@ fn recalculate_zero_graph_all() {
 
   put_testing_edges(&mut graph, "");
 
-  graph.write_recalculate_zero();
+  graph.write_recalculate_zero("");
 
   let res : Vec<(String, String, Weight)> =
     graph.read_graph("", "Uadeb43da4abb", "U000000000000", false, 0, 10000);
@@ -1282,7 +1285,7 @@ fn graph_sort_order() {
 
   put_testing_edges(&mut graph, "");
 
-  graph.write_recalculate_zero();
+  graph.write_recalculate_zero("");
 
   let res : Vec<(String, String, Weight)> =
     graph.read_graph("", "Uadeb43da4abb", "U000000000000", false, 0, 10000);
@@ -1298,7 +1301,7 @@ fn recalculate_zero_graph_duplicates() {
 
   put_testing_edges(&mut graph, "");
 
-  graph.write_recalculate_zero();
+  graph.write_recalculate_zero("");
 
   let res : Vec<(String, String, Weight)> =
     graph.read_graph("", "U000000000000", "Ub01f4ad1b03f", false, 0, 10000);
@@ -1319,7 +1322,7 @@ fn recalculate_zero_graph_positive_only() {
 
   put_testing_edges(&mut graph, "");
 
-  graph.write_recalculate_zero();
+  graph.write_recalculate_zero("");
 
   let res : Vec<(String, String, Weight)> =
     graph.read_graph("", "Uadeb43da4abb", "U000000000000", true, 0, 10000);
@@ -1337,7 +1340,7 @@ fn recalculate_zero_graph_focus_beacon() {
 
   put_testing_edges(&mut graph, "");
 
-  graph.write_recalculate_zero();
+  graph.write_recalculate_zero("");
 
   let res : Vec<(String, String, Weight)> =
       graph.read_graph("", "U95f3426b8e5d", "B79efabc4d8bf", true, 0, 10000);
@@ -1359,13 +1362,13 @@ fn recalculate_zero_reset_perf() {
   let mut graph = AugMultiGraph::new();
 
   put_testing_edges(&mut graph, "");
-  graph.write_recalculate_zero();
+  graph.write_recalculate_zero("");
   graph.reset();
   put_testing_edges(&mut graph, "");
   graph.write_create_context("X");
   graph.write_create_context("Y");
   graph.write_create_context("Z");
-  graph.write_recalculate_zero();
+  graph.write_recalculate_zero("");
 
   let begin    = SystemTime::now();
   let get_time = || SystemTime::now().duration_since(begin).unwrap().as_millis();
@@ -1381,7 +1384,7 @@ fn recalculate_zero_scores() {
 
   put_testing_edges(&mut graph, "");
 
-  graph.write_recalculate_zero();
+  graph.write_recalculate_zero("");
 
   let res : Vec<(String, String, Weight)> =
     graph.read_scores("", "Uadeb43da4abb", "B", true, 100.0, false, -100.0, false, 0, u32::MAX);
@@ -1394,637 +1397,2063 @@ fn recalculate_zero_scores() {
 }
 
 #[test]
-fn scores_sort_order() {
+fn recalculate_zero_incremental_graph_all() {
   let mut graph = AugMultiGraph::new();
 
   put_testing_edges(&mut graph, "");
 
-  graph.write_recalculate_zero();
+  graph.write_recalculate_zero_with_mode("", true);
 
   let res : Vec<(String, String, Weight)> =
-    graph.read_scores("", "Uadeb43da4abb", "B", true, 100.0, false, -100.0, false, 0, u32::MAX);
+    graph.read_graph("", "Uadeb43da4abb", "U000000000000", false, 0, 10000);
 
-  for n in 1..res.len() {
-    assert!(res[n - 1].2.abs() >= res[n].2.abs());
-  }
+  let n = res.len();
+
+  assert!(n > 25);
+  assert!(n < 120);
 }
 
 #[test]
-fn edge_uncontexted() {
+fn recalculate_zero_incremental_no_changes() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("", "U1", "U2", 1.5);
+  put_testing_edges(&mut graph, "");
 
-  let edges : Vec<(String, String, Weight)> = graph.read_edges("");
+  graph.write_recalculate_zero_with_mode("", false);
 
-  let edges_expected : Vec<(String, String, Weight)> = vec![
-    ("U1".to_string(), "U2".to_string(), 1.5)
-  ];
+  let zero = graph.find_or_add_node_by_name("U000000000000");
 
-  assert_eq!(edges, edges_expected);
+  let mut before = graph.all_neighbors("", zero);
+  before.sort_by_key(|x| x.0);
+
+  assert!(!before.is_empty());
+
+  graph.write_recalculate_zero_with_mode("", true);
+
+  let mut after = graph.all_neighbors("", zero);
+  after.sort_by_key(|x| x.0);
+
+  assert_eq!(before, after);
 }
 
 #[test]
-fn edge_contexted() {
+fn recalculate_zero_incremental_new_user() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("X", "U1", "U2", 1.5);
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U2", "U3", 1.0);
+  graph.write_put_edge("", "U3", "U1", 1.0);
 
-  let edges : Vec<(String, String, Weight)> = graph.read_edges("X");
+  graph.write_recalculate_zero_with_mode("", false);
 
-  let edges_expected : Vec<(String, String, Weight)> = vec![
-    ("U1".to_string(), "U2".to_string(), 1.5)
-  ];
+  let zero = graph.find_or_add_node_by_name("U000000000000");
 
-  assert_eq!(edges, edges_expected);
+  assert_eq!(graph.all_neighbors("", zero).len(), 3);
+
+  graph.write_put_edge("", "U1", "U4", 1.0);
+  graph.write_put_edge("", "U4", "U1", 1.0);
+
+  graph.write_recalculate_zero_with_mode("", true);
+
+  let u4 = graph.find_or_add_node_by_name("U4");
+
+  assert_eq!(graph.all_neighbors("", zero).len(), 4);
+  assert!(graph.edge_weight("", zero, u4) > 0.0);
 }
 
 #[test]
-fn null_context_is_sum() {
+fn recalculate_zero_snapshot() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("X", "B1", "U2", 1.0);
-  graph.write_put_edge("Y", "B1", "U2", 2.0);
+  put_testing_edges(&mut graph, "");
 
-  let edges : Vec<(String, String, Weight)> = graph.read_edges("");
+  let mut snapshot = AugMultiGraph::new();
+  snapshot.copy_from(&graph);
 
-  let edges_expected : Vec<(String, String, Weight)> = vec![
-    ("B1".to_string(), "U2".to_string(), 3.0)
-  ];
+  let edges = snapshot.calculate_zero_edges("");
 
-  assert_eq!(edges, edges_expected);
+  let zero = graph.find_or_add_node_by_name("U000000000000");
+
+  assert!(!edges.is_empty());
+  assert!(graph.all_neighbors("", zero).is_empty());
+
+  let payload = rmp_serde::to_vec(&edges).unwrap();
+  let decoded : Vec<(String, Weight)> = rmp_serde::from_slice(payload.as_slice()).unwrap();
+
+  graph.write_set_zero_edges("", &decoded);
+
+  assert_eq!(graph.all_neighbors("", zero).len(), edges.len());
+
+  let res : Vec<(String, String, Weight)> =
+    graph.read_graph("", "Uadeb43da4abb", "U000000000000", false, 0, 10000);
+
+  assert!(res.len() > 25);
+  assert!(res.len() < 120);
 }
 
 #[test]
-fn null_context_contains_all_users() {
+fn recalculate_zero_snapshot_incremental() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("X", "U1", "U2", 1.0);
-  graph.write_put_edge("Y", "U1", "U3", 2.0);
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U2", "U3", 1.0);
+  graph.write_put_edge("", "U3", "U1", 1.0);
 
-  let edges : Vec<(String, String, Weight)> = graph.read_edges("");
+  let mut snapshot = AugMultiGraph::new();
+  snapshot.copy_from(&graph);
 
-  let edges_expected : Vec<(String, String, Weight)> = vec![
-    ("U1".to_string(), "U2".to_string(), 1.0),
-    ("U1".to_string(), "U3".to_string(), 2.0),
-  ];
+  assert_eq!(snapshot.calculate_zero_edges("").len(), 3);
+  assert!(snapshot.zero_dirty.get("").is_none());
+  assert_eq!(snapshot.zero_ranks.get("").unwrap().len(), 3);
 
-  assert_eq!(edges, edges_expected);
-}
+  graph.write_put_edge("", "U3", "U4", 1.0);
+  graph.write_put_edge("", "U4", "U1", 1.0);
+
+  snapshot.copy_from(&graph);
+
+  let u3 = graph.find_or_add_node_by_name("U3");
+  let u4 = graph.find_or_add_node_by_name("U4");
 
+  let mut dirty : Vec<usize> = snapshot.zero_dirty.get("").unwrap().iter().copied().collect();
+  dirty.sort();
+
+  assert_eq!(dirty, vec![u3, u4]);
+  assert_eq!(snapshot.zero_ranks.get("").unwrap().len(), 3);
+
+  assert_eq!(snapshot.calculate_zero_edges("").len(), 4);
+  assert_eq!(snapshot.zero_ranks.get("").unwrap().len(), 4);
+}
 
 #[test]
-fn user_edges_dup() {
+fn set_zero_edges_delta() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("X", "U1", "U2", 1.0);
-  graph.write_put_edge("X", "U1", "U3", 2.0);
-  graph.write_create_context("Y");
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U2", "U3", 1.0);
 
-  let edges : Vec<(String, String, Weight)> = graph.read_edges("Y");
+  graph.write_set_zero_edges("", &[("U1".to_string(), 0.5), ("U2".to_string(), 0.3)]);
+  graph.write_set_zero_edges("", &[("U2".to_string(), 0.4), ("U3".to_string(), 0.2), ("U9".to_string(), 0.1)]);
 
-  let edges_expected : Vec<(String, String, Weight)> = vec![
-    ("U1".to_string(), "U2".to_string(), 1.0),
-    ("U1".to_string(), "U3".to_string(), 2.0),
-  ];
+  let zero = graph.find_or_add_node_by_name("U000000000000");
+  let u1   = graph.find_or_add_node_by_name("U1");
+  let u2   = graph.find_or_add_node_by_name("U2");
+  let u3   = graph.find_or_add_node_by_name("U3");
 
-  assert_eq!(edges, edges_expected);
+  assert_eq!(graph.all_neighbors("", zero).len(), 2);
+  assert_eq!(graph.edge_weight("", zero, u1), 0.0);
+  assert_eq!(graph.edge_weight("", zero, u2), 0.4);
+  assert_eq!(graph.edge_weight("", zero, u3), 0.2);
+  assert!(!graph.node_exists("U9"));
 }
 
 #[test]
-fn non_user_edges_no_dup() {
+fn zero_node_per_context() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("X", "U1", "C2", 1.0);
-  graph.write_put_edge("X", "U1", "C3", 2.0);
-  graph.write_create_context("Y");
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U2", "U3", 1.0);
+  graph.write_put_edge("", "U3", "U1", 1.0);
+  graph.write_put_edge("X", "U1", "B1", 1.0);
+  graph.write_put_edge("X", "U2", "B1", 1.0);
 
-  let edges : Vec<(String, String, Weight)> = graph.read_edges("Y");
+  graph.write_set_zero_node("X", "U0000000000X1", 2);
+  graph.write_recalculate_zero("X");
 
-  assert_eq!(edges.len(), 0);
+  let zero   = graph.find_or_add_node_by_name("U000000000000");
+  let zero_x = graph.find_or_add_node_by_name("U0000000000X1");
+
+  assert_eq!(graph.all_neighbors("X", zero_x).len(), 2);
+  assert!(graph.all_neighbors("", zero_x).is_empty());
+  assert!(graph.all_neighbors("", zero).is_empty());
+  assert!(graph.all_neighbors("X", zero).is_empty());
+
+  graph.write_recalculate_zero("");
+
+  assert_eq!(graph.all_neighbors("", zero).len(), 4);
+  assert_eq!(graph.all_neighbors("X", zero_x).len(), 2);
 }
 
 #[test]
-fn delete_contexted_edge() {
+fn zero_node_per_context_incremental() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("X", "B1", "U2", 1.0);
-  graph.write_put_edge("Y", "B1", "U2", 2.0);
-  graph.write_delete_edge("X", "B1", "U2");
+  graph.write_put_edge("X", "U1", "U2", 1.0);
+  graph.write_put_edge("X", "U2", "U1", 1.0);
 
-  let edges : Vec<(String, String, Weight)> = graph.read_edges("");
+  graph.write_set_zero_node("X", "U0000000000X1", 100);
+  graph.write_recalculate_zero_with_mode("X", true);
 
-  let edges_expected : Vec<(String, String, Weight)> = vec![
-    ("B1".to_string(), "U2".to_string(), 2.0)
-  ];
+  let zero_x = graph.find_or_add_node_by_name("U0000000000X1");
 
-  assert_eq!(edges, edges_expected);
+  assert_eq!(graph.all_neighbors("X", zero_x).len(), 2);
+
+  graph.write_put_edge("X", "U2", "U3", 1.0);
+  graph.write_put_edge("X", "U3", "U1", 1.0);
+  graph.write_recalculate_zero_with_mode("X", true);
+
+  assert_eq!(graph.all_neighbors("X", zero_x).len(), 3);
 }
 
 #[test]
-fn null_context_invariant() {
+fn zero_node_per_context_as_of() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("X", "B1", "B2", 1.0);
-  graph.write_put_edge("Y", "B1", "B2", 2.0);
-  graph.write_delete_edge("X", "B1", "B2");
-  graph.write_put_edge("X", "B1", "B2", 1.0);
+  graph.write_put_edge_at("X", "U1", "U2", 1.0, 100);
 
-  let edges : Vec<(String, String, Weight)> = graph.read_edges("");
+  graph.write_set_zero_node("X", "U0000000000X1", 10);
+  graph.write_set_zero_edges("X", &[("U1".to_string(), 0.5)]);
 
-  let edges_expected : Vec<(String, String, Weight)> = vec![
-    ("B1".to_string(), "B2".to_string(), 3.0)
-  ];
+  let zero_edge = ("U0000000000X1".to_string(), "U1".to_string(), 0.5);
 
-  assert_eq!(edges, edges_expected);
+  assert!(graph.read_edges_as_of("X", u64::MAX).contains(&zero_edge));
+  assert!(!graph.read_edges_as_of("X", 150).contains(&zero_edge));
+  assert!(!graph.read_edges_as_of("", u64::MAX).contains(&zero_edge));
 }
 
 #[test]
-fn scores_uncontexted() {
+fn zero_node_missing_context() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("", "U1", "U2", 2.0);
-  graph.write_put_edge("", "U1", "U3", 1.0);
-  graph.write_put_edge("", "U2", "U3", 3.0);
-
-  let res : Vec<(String, String, Weight)> = graph.read_scores("", "U1", "U", false, 10.0, false, 0.0, false, 0, u32::MAX);
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_recalculate_zero("Y");
 
-  assert_eq!(res.len(), 3);
+  assert_eq!(graph.read_zero_nodes().len(), 1);
+  assert_eq!(graph.read_edges("").len(), 1);
+}
 
-  for x in res {
-    assert_eq!(x.0, "U1");
-    
-    match x.1.as_str() {
-      "U1" => {
-        assert!(x.2 > 0.2);
-        assert!(x.2 < 0.5);
-      },
+#[test]
+fn zero_node_list() {
+  let mut graph = AugMultiGraph::new();
 
-      "U2" => {
-        assert!(x.2 > 0.1);
-        assert!(x.2 < 0.4);
-      },
+  graph.write_set_zero_node("X", "U0000000000X1", 10);
+  graph.write_set_zero_node("Y", "B1", 10);
 
-      "U3" => {
-        assert!(x.2 > 0.2);
-        assert!(x.2 < 0.5);
-      },
+  let res = graph.read_zero_nodes();
 
-      _ => assert!(false),
-    }
-  }
+  assert_eq!(res, vec![
+    ("".to_string(),  "U000000000000".to_string(), 100),
+    ("X".to_string(), "U0000000000X1".to_string(), 10),
+  ]);
 }
 
 #[test]
-fn scores_contexted() {
+fn recalculate_zero_dry_run() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("X", "U1", "U2", 2.0);
-  graph.write_put_edge("X", "U1", "U3", 1.0);
-  graph.write_put_edge("X", "U2", "U3", 3.0);
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U2", "U3", 1.0);
+  graph.write_put_edge("", "U3", "U1", 1.0);
 
-  let res : Vec<(String, String, Weight)> = graph.read_scores("X", "U1", "U", false, 10.0, false, 0.0, false, 0, u32::MAX);
+  let zero = graph.find_or_add_node_by_name("U000000000000");
+
+  let res = graph.write_recalculate_zero_report("", false, true);
 
   assert_eq!(res.len(), 3);
+  for (_, old_amount, new_amount) in res.iter() {
+    assert_eq!(*old_amount, 0.0);
+    assert!(*new_amount > 0.0);
+  }
+  assert!(graph.all_neighbors("", zero).is_empty());
 
-  for x in res {
-    assert_eq!(x.0, "U1");
-    
-    match x.1.as_str() {
-      "U1" => {
-        assert!(x.2 > 0.2);
-        assert!(x.2 < 0.5);
-      },
+  let applied = graph.write_recalculate_zero_report("", true, false);
 
-      "U2" => {
-        assert!(x.2 > 0.1);
-        assert!(x.2 < 0.4);
-      },
+  assert_eq!(applied.len(), 3);
+  assert_eq!(graph.all_neighbors("", zero).len(), 3);
 
-      "U3" => {
-        assert!(x.2 > 0.2);
-        assert!(x.2 < 0.5);
-      },
+  let res = graph.write_recalculate_zero_report("", true, true);
 
-      _ => assert!(false),
-    }
-  }
+  assert!(res.is_empty());
 }
 
 #[test]
-fn scores_unknown_context() {
-  let mut graph = AugMultiGraph::new();
+fn recalculate_zero_dry_run_matches_real_run() {
+  for incremental in [false, true] {
+    let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("X", "B1", "B2", 2.0);
-  graph.write_put_edge("X", "B1", "B3", 1.0);
-  graph.write_put_edge("X", "B2", "B3", 3.0);
+    graph.write_put_edge("", "U1", "U2", 1.0);
+    graph.write_put_edge("", "U2", "U3", 1.0);
+    graph.write_put_edge("", "U3", "U1", 1.0);
 
-  let res : Vec<(String, String, Weight)> = graph.read_scores("Y", "B1", "B", false, 10.0, false, 0.0, false, 0, u32::MAX);
+    graph.write_recalculate_zero_report("", false, false);
 
-  assert_eq!(res.len(), 0);
-}
+    graph.write_put_edge("", "U3", "U4", 1.0);
+    graph.write_put_edge("", "U4", "U1", 1.0);
 
-#[test]
-fn scores_self() {
-  let mut graph = AugMultiGraph::new();
+    let zero = graph.find_or_add_node_by_name("U000000000000");
 
-  graph.write_put_edge("X", "B1", "B2", 2.0);
-  graph.write_put_edge("X", "B1", "B3", 1.0);
-  graph.write_put_edge("X", "B2", "U1", 3.0);
-  graph.write_create_context("Y");
+    let mut before = graph.all_neighbors("", zero);
+    before.sort_by_key(|x| x.0);
 
-  let res : Vec<(String, String, Weight)> = graph.read_scores("Y", "U1", "U", false, 10.0, false, 0.0, false, 0, u32::MAX);
+    let mut preview = graph.write_recalculate_zero_report("", incremental, true);
 
-  assert_eq!(res.len(), 1);
-  assert_eq!(res[0].0, "U1");
-  assert_eq!(res[0].1, "U1");
-  assert!(res[0].2 > 0.999);
-  assert!(res[0].2 < 1.001);
-}
+    let mut after_preview = graph.all_neighbors("", zero);
+    after_preview.sort_by_key(|x| x.0);
 
-#[test]
-fn node_list_uncontexted() {
-  let mut graph = AugMultiGraph::new();
+    assert_eq!(before, after_preview);
 
-  graph.write_put_edge("", "U1", "U2", 2.0);
-  graph.write_put_edge("", "U1", "U3", 1.0);
-  graph.write_put_edge("", "U3", "U2", 3.0);
+    let mut applied = graph.write_recalculate_zero_report("", incremental, false);
 
-  let res : Vec<(String,)> = graph.read_node_list();
+    preview.sort_by(|a, b| a.0.cmp(&b.0));
+    applied.sort_by(|a, b| a.0.cmp(&b.0));
 
-  let mut has_u1 = false;
-  let mut has_u2 = false;
-  let mut has_u3 = false;
+    assert_eq!(preview.len(), applied.len());
 
-  for (x,) in res {
-    match x.as_str() {
-      "U1" => has_u1 = true,
-      "U2" => has_u2 = true,
-      "U3" => has_u3 = true,
-      _    => assert!(false),
+    for ((name_a, old_a, new_a), (name_b, old_b, new_b)) in preview.iter().zip(applied.iter()) {
+      assert_eq!(name_a, name_b);
+      assert_eq!(old_a, old_b);
+      assert!((new_a - new_b).abs() < 0.1);
     }
   }
-
-  assert!(has_u1);
-  assert!(has_u2);
-  assert!(has_u3);
 }
 
 #[test]
-fn node_list_contexted() {
+fn recalculate_zero_report_removed() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("X", "U1", "U2", 2.0);
-  graph.write_put_edge("X", "U1", "U3", 1.0);
-  graph.write_put_edge("X", "U3", "U2", 3.0);
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U2", "U1", 1.0);
+  graph.write_put_edge("", "U1", "U3", 1.0);
 
-  let res : Vec<(String,)> = graph.read_node_list();
+  graph.write_recalculate_zero_report("", false, false);
 
-  let mut has_u1 = false;
-  let mut has_u2 = false;
-  let mut has_u3 = false;
+  graph.write_delete_edge("", "U1", "U3");
 
-  for (x,) in res {
-    match x.as_str() {
-      "U1" => has_u1 = true,
-      "U2" => has_u2 = true,
-      "U3" => has_u3 = true,
-      _    => assert!(false),
-    }
-  }
+  let res = graph.write_recalculate_zero_report("", true, false);
 
-  assert!(has_u1);
-  assert!(has_u2);
-  assert!(has_u3);
+  let removed : Vec<&(String, Weight, Weight)> =
+    res.iter().filter(|(_, _, new_amount)| *new_amount == 0.0).collect();
+
+  assert_eq!(removed.len(), 1);
+  assert_eq!(removed[0].0, "U3");
+  assert!(removed[0].1 > 0.0);
+
+  let zero = graph.find_or_add_node_by_name("U000000000000");
+
+  assert_eq!(graph.all_neighbors("", zero).len(), 2);
 }
 
 #[test]
-fn node_list_mixed() {
+fn recalculate_zero_weighted_pagerank() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("", "U1", "U2", 2.0);
-  graph.write_put_edge("X", "U1", "U3", 1.0);
-  graph.write_put_edge("Y", "U3", "U2", 3.0);
+  graph.centrality = "pagerank".to_string();
 
-  let res : Vec<(String,)> = graph.read_node_list();
+  graph.write_put_edge("", "U1", "U2", 9.0);
+  graph.write_put_edge("", "U1", "U3", 1.0);
+  graph.write_put_edge("", "U2", "U1", 1.0);
+  graph.write_put_edge("", "U3", "U1", 1.0);
 
-  let mut has_u1 = false;
-  let mut has_u2 = false;
-  let mut has_u3 = false;
+  graph.write_recalculate_zero("");
 
-  for (x,) in res {
-    match x.as_str() {
-      "U1" => has_u1 = true,
-      "U2" => has_u2 = true,
-      "U3" => has_u3 = true,
-      _    => assert!(false),
-    }
-  }
+  let zero = graph.find_or_add_node_by_name("U000000000000");
+  let u2   = graph.find_or_add_node_by_name("U2");
+  let u3   = graph.find_or_add_node_by_name("U3");
 
-  assert!(has_u1);
-  assert!(has_u2);
-  assert!(has_u3);
+  assert_eq!(graph.all_neighbors("", zero).len(), 3);
+  assert!(graph.edge_weight("", zero, u2) > graph.edge_weight("", zero, u3));
 }
 
 #[test]
-fn node_score_uncontexted() {
+fn recalculate_zero_personalized_pagerank() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("", "U1", "U2", 2.0);
+  graph.centrality     = "personalized".to_string();
+  graph.pagerank_seeds = vec!["U3".to_string()];
+
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U2", "U1", 1.0);
+  graph.write_put_edge("", "U3", "U4", 1.0);
+  graph.write_put_edge("", "U4", "U3", 1.0);
+
+  graph.write_recalculate_zero("");
+
+  let zero = graph.find_or_add_node_by_name("U000000000000");
+  let u1   = graph.find_or_add_node_by_name("U1");
+  let u2   = graph.find_or_add_node_by_name("U2");
+  let u3   = graph.find_or_add_node_by_name("U3");
+  let u4   = graph.find_or_add_node_by_name("U4");
+
+  //  Only users reachable from the trusted seed get the rank
+  //
+  assert!(graph.edge_weight("", zero, u3) > 0.0);
+  assert!(graph.edge_weight("", zero, u4) > 0.0);
+  assert!(graph.edge_weight("", zero, u3) > graph.edge_weight("", zero, u1));
+  assert!(graph.edge_weight("", zero, u4) > graph.edge_weight("", zero, u2));
+}
+
+#[test]
+fn recalculate_zero_personalized_no_seeds() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.centrality     = "personalized".to_string();
+  graph.pagerank_seeds = vec!["U9".to_string()];
+
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U2", "U1", 1.0);
+  graph.write_put_edge("", "U3", "U4", 1.0);
+  graph.write_put_edge("", "U4", "U3", 1.0);
+
+  graph.write_recalculate_zero("");
+
+  let zero = graph.find_or_add_node_by_name("U000000000000");
+
+  //  Falls back to the uniform distribution
+  //
+  assert_eq!(graph.all_neighbors("", zero).len(), 4);
+}
+
+#[test]
+fn zero_edges_diff_sorted() {
+  let old_edges : HashMap<usize, Weight> = [(1, 0.5), (2, 0.3), (3, 0.1)].into_iter().collect();
+  let new_edges : HashMap<usize, Weight> = [(1, 0.5), (2, 0.1), (4, 0.4)].into_iter().collect();
+
+  let diff = zero_edges_diff(&old_edges, &new_edges);
+
+  assert_eq!(diff.len(), 3);
+  assert_eq!(diff[0], (4, 0.0, 0.4));
+  assert_eq!(diff[1], (2, 0.3, 0.1));
+  assert_eq!(diff[2], (3, 0.1, 0.0));
+}
+
+#[test]
+fn calculate_ranks_parallel_threads() {
+  let mut graph = AugMultiGraph::new();
+
+  for n in 0..8 {
+    graph.write_put_edge("", &format!("U{}", n), &format!("U{}", (n + 1) % 8), 1.0);
+  }
+
+  let users : Vec<usize> =
+    graph.node_infos
+      .iter()
+      .enumerate()
+      .filter(|(_, info)| info.kind == NodeKind::User)
+      .map(|(id, _)| id)
+      .collect();
+
+  let mut walk_graph = graph.graph_from("").clone();
+
+  let mut single   = calculate_ranks_parallel(&mut walk_graph.clone(), &users, 1000, 1);
+  let mut parallel = calculate_ranks_parallel(&mut walk_graph,         &users, 1000, 4);
+
+  single.sort_by_key(|x| x.0);
+  parallel.sort_by_key(|x| x.0);
+
+  assert_eq!(single.len(), users.len());
+  assert_eq!(parallel.len(), users.len());
+
+  for ((id_a, ranks_a), (id_b, ranks_b)) in single.iter().zip(parallel.iter()) {
+    assert_eq!(id_a, id_b);
+
+    let mut nodes_a : Vec<usize> = ranks_a.iter().map(|x| x.0).collect();
+    let mut nodes_b : Vec<usize> = ranks_b.iter().map(|x| x.0).collect();
+
+    nodes_a.sort();
+    nodes_b.sort();
+
+    assert_eq!(nodes_a, nodes_b);
+  }
+}
+
+#[test]
+fn recalculate_zero_threads_same_top_nodes() {
+  let mut top_nodes = vec![];
+
+  for threads in [1, 4] {
+    let mut graph = AugMultiGraph::new();
+
+    graph.recalc_threads = threads;
+
+    put_testing_edges(&mut graph, "");
+
+    graph.write_recalculate_zero("");
+
+    let zero = graph.find_or_add_node_by_name("U000000000000");
+
+    let mut nodes : Vec<String> =
+      graph.all_neighbors("", zero)
+        .into_iter()
+        .map(|(id, _)| graph.node_info_from_id(id).name.clone())
+        .collect();
+
+    nodes.sort();
+
+    assert!(!nodes.is_empty());
+
+    top_nodes.push(nodes);
+  }
+
+  assert_eq!(top_nodes[0], top_nodes[1]);
+}
+
+#[test]
+fn walk_count_upgrade() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U2", "U3", 1.0);
+
+  let u1 = graph.find_or_add_node_by_name("U1");
+
+  let walk_count = |graph : &AugMultiGraph| -> usize {
+    *graph.walk_counts.get("").unwrap().get(&u1).unwrap()
+  };
+
+  let res = graph.read_node_score_with_walks("", "U1", "U2", 100);
+
+  assert_eq!(res.len(), 1);
+  assert!(res[0].2 > 0.0);
+  assert_eq!(walk_count(&graph), 100);
+
+  graph.read_scores_with_walks("", "U1", "", false, 100.0, false, -100.0, false, 0, u32::MAX, 50);
+
+  assert_eq!(walk_count(&graph), 100);
+
+  graph.read_node_score("", "U1", "U2");
+
+  assert_eq!(walk_count(&graph), *NUM_WALK);
+
+  let res = graph.read_mutual_scores_with_walks("", "U1", 10);
+
+  assert!(!res.is_empty());
+  assert_eq!(walk_count(&graph), *NUM_WALK);
+}
+
+#[test]
+fn walk_count_upgrade_kept_after_write() {
+  let mut writable = AugMultiGraph::new();
+  let mut readable = AugMultiGraph::new();
+
+  writable.write_put_edge("", "U1", "U2", 1.0);
+  writable.write_put_edge("", "U2", "U3", 1.0);
+  writable.read_node_score_with_walks("", "U1", "U2", 100);
+  readable.copy_from(&writable);
+
+  let u1 = readable.find_or_add_node_by_name("U1");
+
+  let walk_count = |graph : &AugMultiGraph| -> usize {
+    *graph.walk_counts.get("").unwrap().get(&u1).unwrap()
+  };
+
+  readable.read_node_score_with_walks("", "U1", "U2", 500);
+
+  assert_eq!(walk_count(&readable), 500);
+  assert_eq!(walk_count(&writable), 100);
+
+  for (context, node, num_walk) in readable.take_walk_upgrades() {
+    writable.write_upgrade_walks(context.as_str(), node.as_str(), num_walk);
+  }
+
+  assert!(readable.take_walk_upgrades().is_empty());
+
+  writable.write_put_edge("", "U3", "U4", 1.0);
+  readable.copy_from(&writable);
+
+  assert_eq!(walk_count(&readable), 500);
+}
+
+#[test]
+fn scores_cache_invalidation() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U2", "U3", 1.0);
+  graph.write_put_edge("", "U4", "U5", 1.0);
+
+  let u1 = graph.find_or_add_node_by_name("U1");
+  let u4 = graph.find_or_add_node_by_name("U4");
+
+  let is_cached = |graph : &AugMultiGraph, ego : usize| -> bool {
+    graph.ranks_cache.get("").map(|egos| egos.contains_key(&ego)).unwrap_or(false)
+  };
+
+  graph.read_scores("", "U1", "", false, 100.0, false, -100.0, false, 0, u32::MAX);
+  graph.read_scores("", "U4", "", false, 100.0, false, -100.0, false, 0, u32::MAX);
+
+  assert!(is_cached(&graph, u1));
+  assert!(is_cached(&graph, u4));
+
+  //  U3 is visited by U1 walks only
+  graph.write_put_edge("", "U3", "U6", 1.0);
+
+  assert!(!is_cached(&graph, u1));
+  assert!(is_cached(&graph, u4));
+
+  let res = graph.read_scores("", "U1", "", false, 100.0, false, -100.0, false, 0, u32::MAX);
+
+  assert!(res.iter().any(|(_, dst, _)| dst == "U6"));
+  assert!(is_cached(&graph, u1));
+}
+
+#[test]
+fn scores_cache_copy() {
+  let mut writable = AugMultiGraph::new();
+  let mut readable = AugMultiGraph::new();
+
+  writable.write_put_edge("", "U1", "U2", 1.0);
+  writable.write_put_edge("", "U3", "U4", 1.0);
+  readable.copy_from(&writable);
+
+  let u1 = readable.find_or_add_node_by_name("U1");
+  let u3 = readable.find_or_add_node_by_name("U3");
+
+  readable.read_scores("", "U1", "", false, 100.0, false, -100.0, false, 0, u32::MAX);
+  readable.read_scores("", "U3", "", false, 100.0, false, -100.0, false, 0, u32::MAX);
+
+  writable.write_put_edge("", "U2", "U5", 1.0);
+  readable.copy_from(&writable);
+
+  assert!(!readable.ranks_cache[""].contains_key(&u1));
+  assert!(readable.ranks_cache[""].contains_key(&u3));
+
+  writable.write_reset();
+  readable.copy_from(&writable);
+
+  assert!(readable.ranks_cache.is_empty());
+}
+
+#[test]
+fn scores_paging() {
+  let mut graph = AugMultiGraph::new();
+
+  put_testing_edges(&mut graph, "");
+
+  let all : Vec<(String, String, Weight)> =
+    graph.read_scores("", "Uadeb43da4abb", "", false, 100.0, false, -100.0, false, 0, u32::MAX);
+
+  let page : Vec<(String, String, Weight)> =
+    graph.read_scores("", "Uadeb43da4abb", "", false, 100.0, false, -100.0, false, 2, 5);
+
+  assert!(all.len() >= 5);
+  assert_eq!(page.len(), 3);
+  assert_eq!(page[0].1, all[2].1);
+  assert_eq!(page[2].1, all[4].1);
+}
+
+#[test]
+fn edge_decay_half_life() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.decay.by_context.insert("".to_string(), 100.0);
+
+  let now = now_seconds();
+
+  graph.write_put_edge_at("", "U1", "U2", 4.0, now - 200);
+  graph.write_put_edge_at("", "U1", "U3", 4.0, now);
+
+  let u1 = graph.find_or_add_node_by_name("U1");
+  let u2 = graph.find_or_add_node_by_name("U2");
+  let u3 = graph.find_or_add_node_by_name("U3");
+
+  assert!((graph.edge_weight("", u1, u2) - 1.0).abs() < 0.1);
+  assert!((graph.edge_weight("", u1, u3) - 4.0).abs() < 0.1);
+  assert_eq!(graph.raw_edge_weight("", u1, u2), 4.0);
+}
+
+#[test]
+fn edge_decay_kind() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.decay.by_kind.insert(NodeKind::Beacon, 100.0);
+
+  let now = now_seconds();
+
+  graph.write_put_edge_at("", "U1", "B1", 2.0, now - 100);
+  graph.write_put_edge_at("", "U1", "U2", 2.0, now - 100);
+
+  let u1 = graph.find_or_add_node_by_name("U1");
+  let b1 = graph.find_or_add_node_by_name("B1");
+  let u2 = graph.find_or_add_node_by_name("U2");
+
+  assert!((graph.edge_weight("", u1, b1) - 1.0).abs() < 0.1);
+  assert_eq!(graph.edge_weight("", u1, u2), 2.0);
+}
+
+#[test]
+fn edge_decay_apply() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge_at("", "U1", "U2", 8.0, now_seconds() - 300);
+
+  let u1 = graph.find_or_add_node_by_name("U1");
+  let u2 = graph.find_or_add_node_by_name("U2");
+
+  assert_eq!(graph.edge_weight("", u1, u2), 8.0);
+
+  graph.decay.by_context.insert("".to_string(), 100.0);
+  graph.write_apply_decay("");
+
+  assert!((graph.edge_weight("", u1, u2) - 1.0).abs() < 0.1);
+  assert_eq!(graph.raw_edge_weight("", u1, u2), 8.0);
+}
+
+#[test]
+fn edge_decay_contexted_delta() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.decay.by_context.insert("X".to_string(), 100.0);
+
+  let now = now_seconds();
+
+  graph.write_put_edge_at("X", "U1", "B1", 2.0, now - 100);
+  graph.write_put_edge_at("X", "U1", "B1", 3.0, now - 100);
+
+  let u1 = graph.find_or_add_node_by_name("U1");
+  let b1 = graph.find_or_add_node_by_name("B1");
+
+  assert_eq!(graph.edge_weight("", u1, b1), 3.0);
+  assert!((graph.edge_weight("X", u1, b1) - 1.5).abs() < 0.1);
+}
+
+#[test]
+fn edges_as_of() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge_at("", "U1", "U2", 1.0, 100);
+  graph.write_put_edge_at("", "U1", "U3", 2.0, 200);
+  graph.write_put_edge_at("", "U1", "U2", 3.0, 300);
+  graph.write_delete_edge("", "U1", "U3");
+
+  let mut res = graph.read_edges_as_of("", 250);
+  res.sort_by(|a, b| a.1.cmp(&b.1));
+
+  assert_eq!(res, vec![
+    ("U1".to_string(), "U2".to_string(), 1.0),
+    ("U1".to_string(), "U3".to_string(), 2.0),
+  ]);
+
+  let res = graph.read_edges_as_of("", 50);
+
+  assert!(res.is_empty());
+
+  let res = graph.read_edges_as_of("", u64::MAX);
+
+  assert_eq!(res, vec![("U1".to_string(), "U2".to_string(), 3.0)]);
+}
+
+#[test]
+fn edges_as_of_out_of_order() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge_at("", "U1", "U2", 1.0, 300);
+  graph.write_put_edge_at("", "U1", "U2", 2.0, 100);
+
+  assert_eq!(graph.read_edges_as_of("", 200), vec![("U1".to_string(), "U2".to_string(), 2.0)]);
+  assert_eq!(graph.read_edges_as_of("", 400), vec![("U1".to_string(), "U2".to_string(), 1.0)]);
+}
+
+#[test]
+fn connected_as_of() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge_at("X", "U1", "B1", 1.0, 100);
+  graph.write_put_edge_at("X", "U1", "B2", 1.0, 200);
+
+  let res = graph.read_connected_as_of("X", "U1", 150);
+
+  assert_eq!(res, vec![("U1".to_string(), "B1".to_string())]);
+  assert_eq!(graph.read_connected("X", "U1").len(), 2);
+}
+
+#[test]
+fn node_score_as_of() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge_at("", "U1", "U2", 1.0, 100);
+  graph.write_put_edge_at("", "U1", "U3", 1.0, 200);
+
+  let res = graph.read_node_score_as_of("", "U1", "U3", 0, 150);
+
+  assert_eq!(res.len(), 1);
+  assert_eq!(res[0].2, 0.0);
+
+  let res = graph.read_node_score_as_of("", "U1", "U3", 0, 250);
+
+  assert!(res[0].2 > 0.0);
+}
+
+#[test]
+fn edge_history_copy() {
+  let mut writable = AugMultiGraph::new();
+  let mut readable = AugMultiGraph::new();
+
+  writable.write_put_edge_at("", "U1", "U2", 1.0, 100);
+  readable.copy_from(&writable);
+  writable.write_put_edge_at("", "U1", "U2", 2.0, 200);
+  readable.copy_from(&writable);
+
+  assert_eq!(readable.edge_history[""], writable.edge_history[""]);
+  assert_eq!(readable.edge_history[""].len(), 2);
+
+  writable.write_reset();
+  readable.copy_from(&writable);
+
+  assert!(readable.edge_history.is_empty());
+}
+
+#[test]
+fn graph_sizes() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("",  "U1", "U2", 1.0);
+  graph.write_put_edge("X", "U1", "B1", 1.0);
+
+  let sizes = graph.graph_sizes();
+
+  assert_eq!(sizes.len(), 2);
+  assert_eq!(sizes[0].0, "");
+  assert_eq!(sizes[0].2, 2);
+  assert_eq!(sizes[1].0, "X");
+  assert_eq!(sizes[1].2, 2);
+  assert!(sizes[0].1 >= 3);
+}
+
+#[test]
+fn request_id_encode_decode() {
+  let mut command = Command {
+    id         : CMD_NODE_SCORE.to_string(),
+    context    : "X".to_string(),
+    blocking   : true,
+    payload    : rmp_serde::to_vec(&("U1", "U2")).unwrap(),
+    request_id : String::new(),
+  };
+
+  let decoded = decode_request(encode_request(&command).unwrap().as_slice()).unwrap();
+
+  assert_eq!(decoded.id,         CMD_NODE_SCORE);
+  assert_eq!(decoded.context,    "X");
+  assert_eq!(decoded.payload,    command.payload);
+  assert_eq!(decoded.request_id, "");
+
+  command.request_id = "client-7".to_string();
+
+  let decoded = decode_request(encode_request(&command).unwrap().as_slice()).unwrap();
+
+  assert_eq!(decoded.id,         CMD_NODE_SCORE);
+  assert!(decoded.blocking);
+  assert_eq!(decoded.payload,    command.payload);
+  assert_eq!(decoded.request_id, "client-7");
+}
+
+#[test]
+fn scores_sort_order() {
+  let mut graph = AugMultiGraph::new();
+
+  put_testing_edges(&mut graph, "");
+
+  graph.write_recalculate_zero("");
+
+  let res : Vec<(String, String, Weight)> =
+    graph.read_scores("", "Uadeb43da4abb", "B", true, 100.0, false, -100.0, false, 0, u32::MAX);
+
+  for n in 1..res.len() {
+    assert!(res[n - 1].2.abs() >= res[n].2.abs());
+  }
+}
+
+#[test]
+fn edge_uncontexted() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 1.5);
+
+  let edges : Vec<(String, String, Weight)> = graph.read_edges("");
+
+  let edges_expected : Vec<(String, String, Weight)> = vec![
+    ("U1".to_string(), "U2".to_string(), 1.5)
+  ];
+
+  assert_eq!(edges, edges_expected);
+}
+
+#[test]
+fn edge_contexted() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "U1", "U2", 1.5);
+
+  let edges : Vec<(String, String, Weight)> = graph.read_edges("X");
+
+  let edges_expected : Vec<(String, String, Weight)> = vec![
+    ("U1".to_string(), "U2".to_string(), 1.5)
+  ];
+
+  assert_eq!(edges, edges_expected);
+}
+
+#[test]
+fn null_context_is_sum() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "B1", "U2", 1.0);
+  graph.write_put_edge("Y", "B1", "U2", 2.0);
+
+  let edges : Vec<(String, String, Weight)> = graph.read_edges("");
+
+  let edges_expected : Vec<(String, String, Weight)> = vec![
+    ("B1".to_string(), "U2".to_string(), 3.0)
+  ];
+
+  assert_eq!(edges, edges_expected);
+}
+
+#[test]
+fn null_context_contains_all_users() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "U1", "U2", 1.0);
+  graph.write_put_edge("Y", "U1", "U3", 2.0);
+
+  let edges : Vec<(String, String, Weight)> = graph.read_edges("");
+
+  let edges_expected : Vec<(String, String, Weight)> = vec![
+    ("U1".to_string(), "U2".to_string(), 1.0),
+    ("U1".to_string(), "U3".to_string(), 2.0),
+  ];
+
+  assert_eq!(edges, edges_expected);
+}
+
+
+#[test]
+fn user_edges_dup() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "U1", "U2", 1.0);
+  graph.write_put_edge("X", "U1", "U3", 2.0);
+  graph.write_create_context("Y");
+
+  let edges : Vec<(String, String, Weight)> = graph.read_edges("Y");
+
+  let edges_expected : Vec<(String, String, Weight)> = vec![
+    ("U1".to_string(), "U2".to_string(), 1.0),
+    ("U1".to_string(), "U3".to_string(), 2.0),
+  ];
+
+  assert_eq!(edges, edges_expected);
+}
+
+#[test]
+fn non_user_edges_no_dup() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "U1", "C2", 1.0);
+  graph.write_put_edge("X", "U1", "C3", 2.0);
+  graph.write_create_context("Y");
+
+  let edges : Vec<(String, String, Weight)> = graph.read_edges("Y");
+
+  assert_eq!(edges.len(), 0);
+}
+
+#[test]
+fn delete_contexted_edge() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "B1", "U2", 1.0);
+  graph.write_put_edge("Y", "B1", "U2", 2.0);
+  graph.write_delete_edge("X", "B1", "U2");
+
+  let edges : Vec<(String, String, Weight)> = graph.read_edges("");
+
+  let edges_expected : Vec<(String, String, Weight)> = vec![
+    ("B1".to_string(), "U2".to_string(), 2.0)
+  ];
+
+  assert_eq!(edges, edges_expected);
+}
+
+#[test]
+fn null_context_invariant() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "B1", "B2", 1.0);
+  graph.write_put_edge("Y", "B1", "B2", 2.0);
+  graph.write_delete_edge("X", "B1", "B2");
+  graph.write_put_edge("X", "B1", "B2", 1.0);
+
+  let edges : Vec<(String, String, Weight)> = graph.read_edges("");
+
+  let edges_expected : Vec<(String, String, Weight)> = vec![
+    ("B1".to_string(), "B2".to_string(), 3.0)
+  ];
+
+  assert_eq!(edges, edges_expected);
+}
+
+#[test]
+fn scores_uncontexted() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 2.0);
+  graph.write_put_edge("", "U1", "U3", 1.0);
+  graph.write_put_edge("", "U2", "U3", 3.0);
+
+  let res : Vec<(String, String, Weight)> = graph.read_scores("", "U1", "U", false, 10.0, false, 0.0, false, 0, u32::MAX);
+
+  assert_eq!(res.len(), 3);
+
+  for x in res {
+    assert_eq!(x.0, "U1");
+    
+    match x.1.as_str() {
+      "U1" => {
+        assert!(x.2 > 0.2);
+        assert!(x.2 < 0.5);
+      },
+
+      "U2" => {
+        assert!(x.2 > 0.1);
+        assert!(x.2 < 0.4);
+      },
+
+      "U3" => {
+        assert!(x.2 > 0.2);
+        assert!(x.2 < 0.5);
+      },
+
+      _ => assert!(false),
+    }
+  }
+}
+
+#[test]
+fn scores_contexted() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "U1", "U2", 2.0);
+  graph.write_put_edge("X", "U1", "U3", 1.0);
+  graph.write_put_edge("X", "U2", "U3", 3.0);
+
+  let res : Vec<(String, String, Weight)> = graph.read_scores("X", "U1", "U", false, 10.0, false, 0.0, false, 0, u32::MAX);
+
+  assert_eq!(res.len(), 3);
+
+  for x in res {
+    assert_eq!(x.0, "U1");
+    
+    match x.1.as_str() {
+      "U1" => {
+        assert!(x.2 > 0.2);
+        assert!(x.2 < 0.5);
+      },
+
+      "U2" => {
+        assert!(x.2 > 0.1);
+        assert!(x.2 < 0.4);
+      },
+
+      "U3" => {
+        assert!(x.2 > 0.2);
+        assert!(x.2 < 0.5);
+      },
+
+      _ => assert!(false),
+    }
+  }
+}
+
+#[test]
+fn scores_unknown_context() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "B1", "B2", 2.0);
+  graph.write_put_edge("X", "B1", "B3", 1.0);
+  graph.write_put_edge("X", "B2", "B3", 3.0);
+
+  let res : Vec<(String, String, Weight)> = graph.read_scores("Y", "B1", "B", false, 10.0, false, 0.0, false, 0, u32::MAX);
+
+  assert_eq!(res.len(), 0);
+}
+
+#[test]
+fn scores_self() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "B1", "B2", 2.0);
+  graph.write_put_edge("X", "B1", "B3", 1.0);
+  graph.write_put_edge("X", "B2", "U1", 3.0);
+  graph.write_create_context("Y");
+
+  let res : Vec<(String, String, Weight)> = graph.read_scores("Y", "U1", "U", false, 10.0, false, 0.0, false, 0, u32::MAX);
+
+  assert_eq!(res.len(), 1);
+  assert_eq!(res[0].0, "U1");
+  assert_eq!(res[0].1, "U1");
+  assert!(res[0].2 > 0.999);
+  assert!(res[0].2 < 1.001);
+}
+
+#[test]
+fn node_list_uncontexted() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 2.0);
+  graph.write_put_edge("", "U1", "U3", 1.0);
+  graph.write_put_edge("", "U3", "U2", 3.0);
+
+  let res : Vec<(String,)> = graph.read_node_list();
+
+  let mut has_u1 = false;
+  let mut has_u2 = false;
+  let mut has_u3 = false;
+
+  for (x,) in res {
+    match x.as_str() {
+      "U1" => has_u1 = true,
+      "U2" => has_u2 = true,
+      "U3" => has_u3 = true,
+      _    => assert!(false),
+    }
+  }
+
+  assert!(has_u1);
+  assert!(has_u2);
+  assert!(has_u3);
+}
+
+#[test]
+fn node_list_contexted() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "U1", "U2", 2.0);
+  graph.write_put_edge("X", "U1", "U3", 1.0);
+  graph.write_put_edge("X", "U3", "U2", 3.0);
+
+  let res : Vec<(String,)> = graph.read_node_list();
+
+  let mut has_u1 = false;
+  let mut has_u2 = false;
+  let mut has_u3 = false;
+
+  for (x,) in res {
+    match x.as_str() {
+      "U1" => has_u1 = true,
+      "U2" => has_u2 = true,
+      "U3" => has_u3 = true,
+      _    => assert!(false),
+    }
+  }
+
+  assert!(has_u1);
+  assert!(has_u2);
+  assert!(has_u3);
+}
+
+#[test]
+fn node_list_mixed() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 2.0);
+  graph.write_put_edge("X", "U1", "U3", 1.0);
+  graph.write_put_edge("Y", "U3", "U2", 3.0);
+
+  let res : Vec<(String,)> = graph.read_node_list();
+
+  let mut has_u1 = false;
+  let mut has_u2 = false;
+  let mut has_u3 = false;
+
+  for (x,) in res {
+    match x.as_str() {
+      "U1" => has_u1 = true,
+      "U2" => has_u2 = true,
+      "U3" => has_u3 = true,
+      _    => assert!(false),
+    }
+  }
+
+  assert!(has_u1);
+  assert!(has_u2);
+  assert!(has_u3);
+}
+
+#[test]
+fn node_score_uncontexted() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 2.0);
+  graph.write_put_edge("", "U1", "U3", 1.0);
+  graph.write_put_edge("", "U3", "U2", 3.0);
+
+  let res : Vec<(String, String, Weight)> = graph.read_node_score("", "U1", "U2");
+
+  assert_eq!(res.len(), 1);
+  assert_eq!(res[0].0, "U1");
+  assert_eq!(res[0].1, "U2");
+  assert!(res[0].2 > 0.3);
+  assert!(res[0].2 < 0.45);
+}
+
+#[test]
+fn node_score_contexted() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "U1", "U2", 2.0);
+  graph.write_put_edge("X", "U1", "U3", 1.0);
+  graph.write_put_edge("X", "U3", "U2", 3.0);
+
+  let res : Vec<(String, String, Weight)> = graph.read_node_score("X", "U1", "U2");
+
+  assert_eq!(res.len(), 1);
+  assert_eq!(res[0].0, "U1");
+  assert_eq!(res[0].1, "U2");
+  assert!(res[0].2 > 0.3);
+  assert!(res[0].2 < 0.45);
+}
+
+#[test]
+fn mutual_scores_uncontexted() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 3.0);
+  graph.write_put_edge("", "U1", "U3", 1.0);
+  graph.write_put_edge("", "U2", "U1", 2.0);
+  graph.write_put_edge("", "U2", "U3", 4.0);
+  graph.write_put_edge("", "U3", "U1", 3.0);
+  graph.write_put_edge("", "U3", "U2", 2.0);
+
+  let res : Vec<(String, Weight, Weight)> = graph.read_mutual_scores("", "U1");
+
+  assert_eq!(res.len(), 3);
+
+  let mut u1 = true;
+  let mut u2 = true;
+  let mut u3 = true;
+
+  for x in res.iter() {
+    match x.0.as_str() {
+      "U1" => {
+        assert!(x.1 > 0.3);
+        assert!(x.1 < 0.5);
+        assert!(x.2 > 0.3);
+        assert!(x.2 < 0.5);
+        assert!(u1);
+        u1 = false;
+      },
+
+      "U2" => {
+        assert!(x.1 > 0.25);
+        assert!(x.1 < 0.4);
+        assert!(x.2 > 0.2);
+        assert!(x.2 < 0.35);
+        assert!(u2);
+        u2 = false;
+      },
+
+      "U3" => {
+        assert!(x.1 > 0.2);
+        assert!(x.1 < 0.35);
+        assert!(x.2 > 0.25);
+        assert!(x.2 < 0.35);
+        assert!(u3);
+        u3 = false;
+      },
+
+      _ => {
+        assert!(false);
+      },
+    };
+  }
+}
+
+#[test]
+fn mutual_scores_self() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 3.0);
+  graph.write_delete_edge("", "U1", "U2");
+
+  let res : Vec<(String, Weight, Weight)> = graph.read_mutual_scores("", "U1");
+
+  assert_eq!(res.len(), 1);
+  assert_eq!(res[0].0, "U1");
+  assert!(res[0].1 > 0.999);
+  assert!(res[0].1 < 1.001);
+  assert!(res[0].2 > 0.999);
+  assert!(res[0].2 < 1.001);
+}
+
+#[test]
+fn mutual_scores_contexted() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "U1", "U2", 3.0);
+  graph.write_put_edge("X", "U1", "U3", 1.0);
+  graph.write_put_edge("X", "U2", "U1", 2.0);
+  graph.write_put_edge("X", "U2", "U3", 4.0);
+  graph.write_put_edge("X", "U3", "U1", 3.0);
+  graph.write_put_edge("X", "U3", "U2", 2.0);
+
+  let res : Vec<(String, Weight, Weight)> = graph.read_mutual_scores("X", "U1");
+
+  assert_eq!(res.len(), 3);
+
+  let mut u1 = true;
+  let mut u2 = true;
+  let mut u3 = true;
+
+  for x in res.iter() {
+    match x.0.as_str() {
+      "U1" => {
+        assert!(x.1 > 0.3);
+        assert!(x.1 < 0.5);
+        assert!(x.2 > 0.3);
+        assert!(x.2 < 0.45);
+        assert!(u1);
+        u1 = false;
+      },
+
+      "U2" => {
+        assert!(x.1 > 0.25);
+        assert!(x.1 < 0.4);
+        assert!(x.2 > 0.2);
+        assert!(x.2 < 0.35);
+        assert!(u2);
+        u2 = false;
+      },
+
+      "U3" => {
+        assert!(x.1 > 0.2);
+        assert!(x.1 < 0.35);
+        assert!(x.2 > 0.2);
+        assert!(x.2 < 0.35);
+        assert!(u3);
+        u3 = false;
+      },
+
+      _ => {
+        assert!(false);
+      },
+    };
+  }
+}
+
+#[test]
+fn graph_uncontexted() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 2.0);
   graph.write_put_edge("", "U1", "U3", 1.0);
-  graph.write_put_edge("", "U3", "U2", 3.0);
+  graph.write_put_edge("", "U2", "U3", 3.0);
 
-  let res : Vec<(String, String, Weight)> = graph.read_node_score("", "U1", "U2");
+  let res : Vec<(String, String, Weight)> = graph.read_graph("", "U1", "U2", false, 0, 10000);
 
-  assert_eq!(res.len(), 1);
-  assert_eq!(res[0].0, "U1");
-  assert_eq!(res[0].1, "U2");
-  assert!(res[0].2 > 0.3);
-  assert!(res[0].2 < 0.45);
+  assert_eq!(res.len(), 2);
+
+  let mut has_u1 = false;
+  let mut has_u2 = false;
+
+  for x in res {
+    match x.0.as_str() {
+      "U1" => {
+        assert_eq!(x.1, "U2");
+        assert!(x.2 > 0.65);
+        assert!(x.2 < 0.67);
+        has_u1 = true;
+      },
+
+      "U2" => {
+        assert_eq!(x.1, "U3");
+        assert!(x.2 > 0.99);
+        assert!(x.2 < 1.01);
+        has_u2 = true;
+      },
+
+      _ => panic!(),
+    }
+  }
+
+  assert!(has_u1);
+  assert!(has_u2);
+}
+
+#[test]
+fn graph_contexted() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("X", "U1", "U2", 2.0);
+  graph.write_put_edge("X", "U1", "U3", 1.0);
+  graph.write_put_edge("X", "U2", "U3", 3.0);
+
+  let res : Vec<(String, String, Weight)> = graph.read_graph("X", "U1", "U2", false, 0, 10000);
+
+  assert_eq!(res.len(), 2);
+
+  let mut has_u1 = false;
+  let mut has_u2 = false;
+
+  for x in res {
+    match x.0.as_str() {
+      "U1" => {
+        assert_eq!(x.1, "U2");
+        assert!(x.2 > 0.65);
+        assert!(x.2 < 0.67);
+        has_u1 = true;
+      },
+
+      "U2" => {
+        assert_eq!(x.1, "U3");
+        assert!(x.2 > 0.99);
+        assert!(x.2 < 1.01);
+        has_u2 = true;
+      },
+
+      _ => panic!(),
+    }
+  }
+
+  assert!(has_u1);
+  assert!(has_u2);
+}
+
+#[test]
+fn graph_empty() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 2.0);
+  graph.write_put_edge("", "U1", "U3", 1.0);
+  graph.write_put_edge("", "U2", "U3", 3.0);
+
+  graph.write_delete_edge("", "U1", "U2");
+  graph.write_delete_edge("", "U1", "U3");
+  graph.write_delete_edge("", "U2", "U3");
+
+  let res : Vec<(String, String, Weight)> = graph.read_graph("", "U1", "U2", false, 0, 10000);
+
+  for x in res.iter() {
+    println!("{} -> {}: {}", x.0, x.1, x.2);
+  }
+
+  assert_eq!(res.len(), 0);
+}
+
+#[test]
+fn graph_removed_edge() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "B2", 1.0);
+  graph.write_put_edge("", "B2", "U1", 2.0);
+  graph.write_put_edge("", "B2", "C2", 1.0);
+  graph.write_put_edge("", "B2", "C3", 1.5);
+  graph.write_put_edge("", "B2", "C4", 3.0);
+
+  graph.write_delete_edge("", "U1", "B2");
+
+  let res : Vec<(String, String, Weight)> = graph.read_graph("", "U1", "B2", false, 0, 10000);
+
+  for x in res.iter() {
+    println!("{} -> {}: {}", x.0, x.1, x.2);
+  }
+
+  assert_eq!(res.len(), 0);
+}
+
+#[test]
+fn graph_num_paths() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 3.0);
+  graph.write_put_edge("", "U1", "U3", 1.0);
+  graph.write_put_edge("", "U2", "U4", 1.0);
+  graph.write_put_edge("", "U3", "U4", 1.0);
+
+  let res : Vec<(String, String, Weight)> = graph.read_graph_paths("", "U1", "U4", &GraphQuery {
+    positive_only   : false,
+    path_cost       : "positive",
+    num_paths       : 1,
+    max_path_length : u32::MAX,
+    index           : 0,
+    count           : 10000,
+  });
+
+  assert_eq!(res.len(), 2);
+
+  for x in res.iter() {
+    assert!((x.0 == "U1" && x.1 == "U2") || (x.0 == "U2" && x.1 == "U4"));
+  }
+
+  let res : Vec<(String, String, Weight)> = graph.read_graph_paths("", "U1", "U4", &GraphQuery {
+    positive_only   : false,
+    path_cost       : "positive",
+    num_paths       : 2,
+    max_path_length : u32::MAX,
+    index           : 0,
+    count           : 10000,
+  });
+
+  assert_eq!(res.len(), 4);
+}
+
+#[test]
+fn graph_max_path_length() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 3.0);
+  graph.write_put_edge("", "U2", "U4", 1.0);
+
+  let res : Vec<(String, String, Weight)> = graph.read_graph_paths("", "U1", "U4", &GraphQuery {
+    positive_only   : false,
+    path_cost       : "positive",
+    num_paths       : 1,
+    max_path_length : 1,
+    index           : 0,
+    count           : 10000,
+  });
+
+  assert_eq!(res.len(), 0);
+
+  let res : Vec<(String, String, Weight)> = graph.read_graph_paths("", "U1", "U4", &GraphQuery {
+    positive_only   : false,
+    path_cost       : "positive",
+    num_paths       : 1,
+    max_path_length : 2,
+    index           : 0,
+    count           : 10000,
+  });
+
+  assert_eq!(res.len(), 2);
+}
+
+#[test]
+fn explain_score_chains() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 3.0);
+  graph.write_put_edge("", "U1", "U3", 1.0);
+  graph.write_put_edge("", "U2", "U4", 1.0);
+  graph.write_put_edge("", "U3", "U4", 1.0);
+
+  let res : Vec<(u32, String, String, Weight, Weight)> = graph.read_explain_score("", "U1", "U4", "positive", 2, u32::MAX);
+
+  assert_eq!(res.len(), 4);
+
+  assert_eq!(res[0].0, 0);
+  assert_eq!(res[0].1, "U1");
+  assert_eq!(res[0].2, "U2");
+  assert!(res[0].3 > 0.749);
+  assert!(res[0].3 < 0.751);
+  assert!(res[0].4 > 0.749);
+  assert!(res[0].4 < 0.751);
+
+  assert_eq!(res[1].0, 0);
+  assert_eq!(res[1].1, "U2");
+  assert_eq!(res[1].2, "U4");
+  assert!(res[1].3 > 0.999);
+  assert!(res[1].3 < 1.001);
+
+  assert_eq!(res[2].0, 1);
+  assert_eq!(res[2].1, "U1");
+  assert_eq!(res[2].2, "U3");
+  assert!(res[2].4 > 0.249);
+  assert!(res[2].4 < 0.251);
+
+  assert_eq!(res[3].0, 1);
+  assert_eq!(res[3].1, "U3");
+  assert_eq!(res[3].2, "U4");
+}
+
+#[test]
+fn explain_score_collapse_beacons() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "B1", 1.0);
+  graph.write_put_edge("", "B1", "U2", 1.0);
+  graph.write_put_edge("", "U2", "U3", 1.0);
+
+  let res : Vec<(u32, String, String, Weight, Weight)> = graph.read_explain_score("", "U1", "U3", "positive", 1, u32::MAX);
+
+  assert_eq!(res.len(), 2);
+  assert_eq!(res[0].1, "U1");
+  assert_eq!(res[0].2, "U2");
+  assert_eq!(res[1].1, "U2");
+  assert_eq!(res[1].2, "U3");
+  assert!(res[1].4 > 0.999);
+  assert!(res[1].4 < 1.001);
+}
+
+#[test]
+fn graph_path_cost_signed_normalized() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2",  1.0);
+  graph.write_put_edge("", "U1", "U3",  1.0);
+  graph.write_put_edge("", "U2", "U5",  1.0);
+  graph.write_put_edge("", "U2", "U7",  0.6);
+  graph.write_put_edge("", "U3", "U6", -1.0);
+  graph.write_put_edge("", "U3", "U8",  1.0);
+  graph.write_put_edge("", "U5", "U4",  1.0);
+  graph.write_put_edge("", "U6", "U4",  1.0);
+
+  //  Negative edges are normalized by the sum of absolute weights
+  //  like positive edges, so U3 -> U6 is a half of U3 outgoing weight.
+  //
+  let res : Vec<(u32, String, String, Weight, Weight)> = graph.read_explain_score("", "U1", "U4", "signed", 1, u32::MAX);
+
+  assert_eq!(res.len(), 3);
+  assert_eq!(res[0].1, "U1");
+  assert_eq!(res[0].2, "U2");
+  assert_eq!(res[1].1, "U2");
+  assert_eq!(res[1].2, "U5");
+  assert_eq!(res[2].1, "U5");
+  assert_eq!(res[2].2, "U4");
+}
+
+#[test]
+fn graph_path_cost_signed() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2",  1.0);
+  graph.write_put_edge("", "U1", "U3", -1.0);
+  graph.write_put_edge("", "U3", "U4",  1.0);
+
+  let res : Vec<(String, String, Weight)> = graph.read_graph_paths("", "U1", "U4", &GraphQuery {
+    positive_only   : false,
+    path_cost       : "positive",
+    num_paths       : 1,
+    max_path_length : u32::MAX,
+    index           : 0,
+    count           : 10000,
+  });
+
+  assert_eq!(res.len(), 0);
+
+  let res : Vec<(String, String, Weight)> = graph.read_graph_paths("", "U1", "U4", &GraphQuery {
+    positive_only   : false,
+    path_cost       : "signed",
+    num_paths       : 1,
+    max_path_length : u32::MAX,
+    index           : 0,
+    count           : 10000,
+  });
+
+  assert_eq!(res.len(), 2);
+
+  for x in res.iter() {
+    match x.0.as_str() {
+      "U1" => {
+        assert_eq!(x.1, "U3");
+        assert!(x.2 < -0.499);
+        assert!(x.2 > -0.501);
+      },
+
+      "U3" => {
+        assert_eq!(x.1, "U4");
+        assert!(x.2 > 0.999);
+        assert!(x.2 < 1.001);
+      },
+
+      _ => panic!(),
+    }
+  }
+}
+
+#[test]
+fn explain_score_signed_negative_edge() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2",  1.0);
+  graph.write_put_edge("", "U1", "U3", -3.0);
+  graph.write_put_edge("", "U3", "U4",  2.0);
+  graph.write_put_edge("", "U3", "U5", -2.0);
+
+  //  Hop weights are normalized by the sum of absolute weights,
+  //  same as the signed path cost.
+  //
+  let res : Vec<(u32, String, String, Weight, Weight)> = graph.read_explain_score("", "U1", "U4", "signed", 1, u32::MAX);
+
+  assert_eq!(res.len(), 2);
+
+  assert_eq!(res[0].1, "U1");
+  assert_eq!(res[0].2, "U3");
+  assert!(res[0].3 < -0.749);
+  assert!(res[0].3 > -0.751);
+
+  assert_eq!(res[1].1, "U3");
+  assert_eq!(res[1].2, "U4");
+  assert!(res[1].3 > 0.499);
+  assert!(res[1].3 < 0.501);
+
+  for x in res.iter() {
+    assert!(x.3.abs() <= 1.0);
+    assert!(x.4 < -0.374);
+    assert!(x.4 > -0.376);
+  }
+}
+
+#[test]
+fn graph_path_cost_score() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2",   2.0);
+  graph.write_put_edge("", "U1", "U3",   1.0);
+  graph.write_put_edge("", "U1", "U5",   3.0);
+  graph.write_put_edge("", "U5", "U2", -10.0);
+  graph.write_put_edge("", "U2", "U4",   1.0);
+  graph.write_put_edge("", "U3", "U4",   1.0);
+
+  let res : Vec<(u32, String, String, Weight, Weight)> = graph.read_explain_score("", "U1", "U4", "positive", 1, u32::MAX);
+
+  assert_eq!(res.len(), 2);
+  assert_eq!(res[0].2, "U2");
+
+  let res : Vec<(u32, String, String, Weight, Weight)> = graph.read_explain_score("", "U1", "U4", "score", 1, u32::MAX);
+
+  assert_eq!(res.len(), 2);
+  assert_eq!(res[0].2, "U3");
+
+  let res : Vec<(u32, String, String, Weight, Weight)> = graph.read_explain_score("", "U1", "U4", "unknown", 1, u32::MAX);
+
+  assert_eq!(res.len(), 0);
+}
+
+#[test]
+fn neighborhood_depth() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U2", "U3", 1.0);
+  graph.write_put_edge("", "U3", "U4", 1.0);
+
+  let res : Vec<(String, String, Weight)> = graph.read_neighborhood("", "U1", 2, true, true, 100, 0, 10000);
+
+  assert_eq!(res.len(), 2);
+
+  for x in res.iter() {
+    assert!((x.0 == "U1" && x.1 == "U2") || (x.0 == "U2" && x.1 == "U3"));
+  }
+
+  let res : Vec<(String, String, Weight)> = graph.read_neighborhood("", "U1", 3, true, true, 100, 0, 10000);
+
+  assert_eq!(res.len(), 3);
+}
+
+#[test]
+fn neighborhood_collapse() {
+  let mut graph = AugMultiGraph::new();
+
+  graph.write_put_edge("", "U1", "B1", 1.0);
+  graph.write_put_edge("", "B1", "U2", 1.0);
+  graph.write_put_edge("", "U2", "U3", 1.0);
+
+  let res : Vec<(String, String, Weight)> = graph.read_neighborhood("", "U1", 2, true, true, 100, 0, 10000);
+
+  assert_eq!(res.len(), 2);
+
+  for x in res.iter() {
+    assert!((x.0 == "U1" && x.1 == "U2") || (x.0 == "U2" && x.1 == "U3"));
+  }
+
+  let res : Vec<(String, String, Weight)> = graph.read_neighborhood("", "U1", 2, false, true, 100, 0, 10000);
+
+  assert_eq!(res.len(), 2);
+
+  for x in res.iter() {
+    assert!((x.0 == "U1" && x.1 == "B1") || (x.0 == "B1" && x.1 == "U2"));
+  }
 }
 
 #[test]
-fn node_score_contexted() {
+fn neighborhood_max_nodes() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("X", "U1", "U2", 2.0);
-  graph.write_put_edge("X", "U1", "U3", 1.0);
-  graph.write_put_edge("X", "U3", "U2", 3.0);
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U1", "U3", 5.0);
+  graph.write_put_edge("", "U1", "U4", 1.0);
 
-  let res : Vec<(String, String, Weight)> = graph.read_node_score("X", "U1", "U2");
+  let res : Vec<(String, String, Weight)> = graph.read_neighborhood("", "U1", 1, true, true, 2, 0, 10000);
 
   assert_eq!(res.len(), 1);
   assert_eq!(res[0].0, "U1");
-  assert_eq!(res[0].1, "U2");
-  assert!(res[0].2 > 0.3);
-  assert!(res[0].2 < 0.45);
+  assert_eq!(res[0].1, "U3");
+  assert!(res[0].2 > 0.714);
+  assert!(res[0].2 < 0.715);
 }
 
 #[test]
-fn mutual_scores_uncontexted() {
+fn new_edges_fetch() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("", "U1", "U2", 3.0);
-  graph.write_put_edge("", "U1", "U3", 1.0);
-  graph.write_put_edge("", "U2", "U1", 2.0);
-  graph.write_put_edge("", "U2", "U3", 4.0);
-  graph.write_put_edge("", "U3", "U1", 3.0);
-  graph.write_put_edge("", "U3", "U2", 2.0);
+  graph.write_put_edge("", "U1", "U2", 1.0);
 
-  let res : Vec<(String, Weight, Weight)> = graph.read_mutual_scores("", "U1");
+  assert_eq!(graph.write_fetch_new_edges("U1", "B").len(), 0);
 
-  assert_eq!(res.len(), 3);
+  graph.write_put_edge("", "U1", "B3", 2.0);
+  graph.write_put_edge("", "U2", "B4", 3.0);
 
-  let mut u1 = true;
-  let mut u2 = true;
-  let mut u3 = true;
+  let beacons = graph.write_fetch_new_edges("U1", "B");
 
-  for x in res.iter() {
-    match x.0.as_str() {
-      "U1" => {
-        assert!(x.1 > 0.3);
-        assert!(x.1 < 0.5);
-        assert!(x.2 > 0.3);
-        assert!(x.2 < 0.5);
-        assert!(u1);
-        u1 = false;
-      },
+  assert_eq!(beacons.len(), 2);
+  assert_eq!(beacons[0].0, "B3");
+  assert_eq!(beacons[1].0, "B4");
 
-      "U2" => {
-        assert!(x.1 > 0.25);
-        assert!(x.1 < 0.4);
-        assert!(x.2 > 0.2);
-        assert!(x.2 < 0.35);
-        assert!(u2);
-        u2 = false;
-      },
+  assert_eq!(graph.write_fetch_new_edges("U1", "B").len(), 0);
+}
 
-      "U3" => {
-        assert!(x.1 > 0.2);
-        assert!(x.1 < 0.35);
-        assert!(x.2 > 0.25);
-        assert!(x.2 < 0.35);
-        assert!(u3);
-        u3 = false;
-      },
+#[test]
+fn bloom_filter_round_trip_random() {
+  let mut rng = rand::thread_rng();
 
-      _ => {
-        assert!(false);
-      },
-    };
+  for _ in 0..200 {
+    let num_hashes = rng.gen_range(1..32);
+    let words : Vec<u64> = (0..rng.gen_range(0..64)).map(|_| rng.gen()).collect();
+
+    let bytes = bloom_filter_encode(num_hashes, &words);
+
+    assert_eq!(bytes.len(), BLOOM_FILTER_HEADER_SIZE + words.len() * 8);
+    assert_eq!(bloom_filter_decode(&bytes), Ok((num_hashes, words)));
   }
 }
 
 #[test]
-fn mutual_scores_self() {
+fn bloom_filter_decode_invalid() {
+  let mut bytes = bloom_filter_encode(10, &[1, 2, 3]);
+
+  assert!(bloom_filter_decode(&bytes[..bytes.len() - 1]).is_err());
+  assert!(bloom_filter_decode(&bytes[..5]).is_err());
+  assert!(bloom_filter_decode(&[0; 32]).is_err());
+
+  bytes[0] = 2;
+  assert!(bloom_filter_decode(&bytes).is_err());
+
+  assert!(bloom_filter_decode(&bloom_filter_encode(0, &[1])).is_err());
+}
+
+#[test]
+fn new_edges_filter_round_trip() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("", "U1", "U2", 3.0);
-  graph.write_delete_edge("", "U1", "U2");
+  graph.write_put_edge("", "U1", "B1", 1.0);
+  graph.write_put_edge("", "U1", "B2", 2.0);
+  graph.write_put_edge("", "U1", "B3", 3.0);
 
-  let res : Vec<(String, Weight, Weight)> = graph.read_mutual_scores("", "U1");
+  assert_eq!(graph.write_fetch_new_edges("U1", "B").len(), 3);
 
-  assert_eq!(res.len(), 1);
-  assert_eq!(res[0].0, "U1");
-  assert!(res[0].1 > 0.999);
-  assert!(res[0].1 < 1.001);
-  assert!(res[0].2 > 0.999);
-  assert!(res[0].2 < 1.001);
+  let filter = graph.read_new_edges_filter("U1");
+
+  let mut other = AugMultiGraph::new();
+
+  other.write_put_edge("", "U1", "B1", 1.0);
+  other.write_put_edge("", "U1", "B2", 2.0);
+  other.write_put_edge("", "U1", "B3", 3.0);
+  other.write_new_edges_filter("U1", &filter);
+
+  assert_eq!(other.read_new_edges_filter("U1"), filter);
+  assert_eq!(other.write_fetch_new_edges("U1", "B").len(), 0);
 }
 
 #[test]
-fn mutual_scores_contexted() {
+fn new_edges_filter_rejects_invalid() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("X", "U1", "U2", 3.0);
-  graph.write_put_edge("X", "U1", "U3", 1.0);
-  graph.write_put_edge("X", "U2", "U1", 2.0);
-  graph.write_put_edge("X", "U2", "U3", 4.0);
-  graph.write_put_edge("X", "U3", "U1", 3.0);
-  graph.write_put_edge("X", "U3", "U2", 2.0);
+  graph.write_put_edge("", "U1", "B1", 1.0);
+  graph.write_fetch_new_edges("U1", "B");
 
-  let res : Vec<(String, Weight, Weight)> = graph.read_mutual_scores("X", "U1");
+  let filter = graph.read_new_edges_filter("U1");
 
-  assert_eq!(res.len(), 3);
+  graph.write_new_edges_filter("U1", &filter[..filter.len() - 1]);
+  assert_eq!(graph.read_new_edges_filter("U1"), filter);
 
-  let mut u1 = true;
-  let mut u2 = true;
-  let mut u3 = true;
+  let too_large = SeenFilter::new(SeenFilterKind::Bloom, *FILTER_MAX_SIZE * 2, 4);
 
-  for x in res.iter() {
-    match x.0.as_str() {
-      "U1" => {
-        assert!(x.1 > 0.3);
-        assert!(x.1 < 0.5);
-        assert!(x.2 > 0.3);
-        assert!(x.2 < 0.45);
-        assert!(u1);
-        u1 = false;
-      },
+  graph.write_new_edges_filter("U1", &too_large.to_bytes());
+  assert_eq!(graph.read_new_edges_filter("U1"), filter);
+}
 
-      "U2" => {
-        assert!(x.1 > 0.25);
-        assert!(x.1 < 0.4);
-        assert!(x.2 > 0.2);
-        assert!(x.2 < 0.35);
-        assert!(u2);
-        u2 = false;
-      },
+#[test]
+fn mark_seen_bitmap_max_size() {
+  let mut graph = AugMultiGraph::new();
 
-      "U3" => {
-        assert!(x.1 > 0.2);
-        assert!(x.1 < 0.35);
-        assert!(x.2 > 0.2);
-        assert!(x.2 < 0.35);
-        assert!(u3);
-        u3 = false;
-      },
+  graph.write_put_edge("", "U1", "B1", 1.0);
 
-      _ => {
-        assert!(false);
-      },
-    };
+  graph.write_new_edges_filter("U1", &SeenFilter::new(SeenFilterKind::Bitmap, 0, 0).to_bytes());
+
+  while graph.node_count <= *FILTER_MAX_SIZE * 8 {
+    let name = format!("B{}", graph.node_count);
+    graph.find_or_add_node_by_name(&name);
   }
+
+  let last = graph.node_infos[graph.node_count - 1].name.clone();
+
+  graph.write_mark_seen("U1", &["B1", last.as_str()]);
+
+  assert_eq!(graph.read_is_seen("U1", &["B1", last.as_str()]), vec![
+    ("B1".to_string(), true),
+    (last.clone(),     false),
+  ]);
+
+  let (stats, _) = graph.read_seen_filter_stats();
+
+  assert_eq!(stats.len(), 1);
+  assert!(stats[0].3 <= *FILTER_MAX_SIZE as u64);
 }
 
 #[test]
-fn graph_uncontexted() {
+fn fetch_new_edges_by_kind() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("", "U1", "U2", 2.0);
-  graph.write_put_edge("", "U1", "U3", 1.0);
-  graph.write_put_edge("", "U2", "U3", 3.0);
+  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U1", "B3", 2.0);
+  graph.write_put_edge("", "U2", "C4", 3.0);
 
-  let res : Vec<(String, String, Weight)> = graph.read_graph("", "U1", "U2", false, 0, 10000);
+  let res = graph.write_fetch_new_edges_by_kind("", "U1", &["B", "C"]);
 
   assert_eq!(res.len(), 2);
+  assert_eq!(res[0].0, "B3");
+  assert_eq!(res[1].0, "C4");
 
-  let mut has_u1 = false;
-  let mut has_u2 = false;
+  let res = graph.write_fetch_new_edges_by_kind("", "U1", &[]);
 
-  for x in res {
-    match x.0.as_str() {
-      "U1" => {
-        assert_eq!(x.1, "U2");
-        assert!(x.2 > 0.65);
-        assert!(x.2 < 0.67);
-        has_u1 = true;
-      },
+  assert_eq!(res.len(), 2);
+  assert!(res.iter().any(|(name, _)| name == "U1"));
+  assert!(res.iter().any(|(name, _)| name == "U2"));
 
-      "U2" => {
-        assert_eq!(x.1, "U3");
-        assert!(x.2 > 0.99);
-        assert!(x.2 < 1.01);
-        has_u2 = true;
-      },
+  assert!(graph.write_fetch_new_edges_by_kind("", "U1", &[]).is_empty());
+  assert!(graph.write_fetch_new_edges_by_kind("", "U1", &["X"]).is_empty());
+}
 
-      _ => panic!(),
-    }
-  }
+#[test]
+fn fetch_new_edges_context() {
+  let mut graph = AugMultiGraph::new();
 
-  assert!(has_u1);
-  assert!(has_u2);
+  graph.write_put_edge("X", "U1", "B1", 1.0);
+  graph.write_put_edge("Y", "U1", "B2", 1.0);
+
+  let res = graph.write_fetch_new_edges_by_kind("Y", "U1", &["B"]);
+
+  assert_eq!(res.len(), 1);
+  assert_eq!(res[0].0, "B2");
+
+  let res = graph.write_fetch_new_edges_by_kind("X", "U1", &["B"]);
+
+  assert_eq!(res.len(), 1);
+  assert_eq!(res[0].0, "B1");
+
+  assert!(graph.write_fetch_new_edges_by_kind("Z", "U1", &["B"]).is_empty());
 }
 
 #[test]
-fn graph_contexted() {
+fn fetch_new_edges_paged() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("X", "U1", "U2", 2.0);
-  graph.write_put_edge("X", "U1", "U3", 1.0);
-  graph.write_put_edge("X", "U2", "U3", 3.0);
+  graph.write_put_edge("", "U1", "B1", 1.0);
+  graph.write_put_edge("", "U1", "B2", 3.0);
+  graph.write_put_edge("", "U1", "B3", 2.0);
+  graph.write_put_edge("", "U1", "B4", 0.1);
 
-  let res : Vec<(String, String, Weight)> = graph.read_graph("X", "U1", "U2", false, 0, 10000);
+  let page = graph.write_fetch_new_edges_paged("", "U1", &["B"], 0.0, 2);
 
-  assert_eq!(res.len(), 2);
+  assert_eq!(page.len(), 2);
+  assert_eq!(page[0].0, "B2");
+  assert_eq!(page[1].0, "B3");
+  assert!(page[0].1 >= page[1].1);
 
-  let mut has_u1 = false;
-  let mut has_u2 = false;
+  let page = graph.write_fetch_new_edges_paged("", "U1", &["B"], 0.0, 2);
 
-  for x in res {
-    match x.0.as_str() {
-      "U1" => {
-        assert_eq!(x.1, "U2");
-        assert!(x.2 > 0.65);
-        assert!(x.2 < 0.67);
-        has_u1 = true;
-      },
+  assert_eq!(page.len(), 2);
+  assert_eq!(page[0].0, "B1");
+  assert_eq!(page[1].0, "B4");
 
-      "U2" => {
-        assert_eq!(x.1, "U3");
-        assert!(x.2 > 0.99);
-        assert!(x.2 < 1.01);
-        has_u2 = true;
-      },
+  assert!(graph.write_fetch_new_edges_paged("", "U1", &["B"], 0.0, 2).is_empty());
+}
 
-      _ => panic!(),
-    }
-  }
+#[test]
+fn fetch_new_edges_min_score() {
+  let mut graph = AugMultiGraph::new();
 
-  assert!(has_u1);
-  assert!(has_u2);
+  graph.write_put_edge("", "U1", "B1", 10.0);
+  graph.write_put_edge("", "U1", "B2", 0.01);
+
+  let min_score = graph.read_node_score("", "U1", "B2")[0].2 * 2.0;
+
+  let page = graph.write_fetch_new_edges_paged("", "U1", &["B"], min_score, 10);
+
+  assert_eq!(page.len(), 1);
+  assert_eq!(page[0].0, "B1");
+
+  let page = graph.write_fetch_new_edges_paged("", "U1", &["B"], 0.0, 10);
+
+  assert_eq!(page.len(), 1);
+  assert_eq!(page[0].0, "B2");
 }
 
 #[test]
-fn graph_empty() {
+fn mark_seen() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("", "U1", "U2", 2.0);
-  graph.write_put_edge("", "U1", "U3", 1.0);
-  graph.write_put_edge("", "U2", "U3", 3.0);
+  graph.write_put_edge("", "U1", "B1", 1.0);
+  graph.write_put_edge("", "U1", "B2", 2.0);
 
-  graph.write_delete_edge("", "U1", "U2");
-  graph.write_delete_edge("", "U1", "U3");
-  graph.write_delete_edge("", "U2", "U3");
+  graph.write_mark_seen("U1", &["B1", "B3"]);
 
-  let res : Vec<(String, String, Weight)> = graph.read_graph("", "U1", "U2", false, 0, 10000);
+  assert_eq!(graph.read_is_seen("U1", &["B1", "B2", "B3"]), vec![
+    ("B1".to_string(), true),
+    ("B2".to_string(), false),
+    ("B3".to_string(), false),
+  ]);
 
-  for x in res.iter() {
-    println!("{} -> {}: {}", x.0, x.1, x.2);
-  }
+  let res = graph.write_fetch_new_edges_by_kind("", "U1", &["B"]);
 
-  assert_eq!(res.len(), 0);
+  assert_eq!(res.len(), 1);
+  assert_eq!(res[0].0, "B2");
+  assert_eq!(graph.read_is_seen("U1", &["B2"]), vec![("B2".to_string(), true)]);
+
+  graph.write_mark_seen("U9", &["B1"]);
+
+  assert!(!graph.node_exists("U9"));
 }
 
 #[test]
-fn graph_removed_edge() {
+fn clear_seen() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("", "U1", "B2", 1.0);
-  graph.write_put_edge("", "B2", "U1", 2.0);
-  graph.write_put_edge("", "B2", "C2", 1.0);
-  graph.write_put_edge("", "B2", "C3", 1.5);
-  graph.write_put_edge("", "B2", "C4", 3.0);
+  graph.write_put_edge("", "U1", "B1", 1.0);
 
-  graph.write_delete_edge("", "U1", "B2");
+  assert_eq!(graph.write_fetch_new_edges_by_kind("", "U1", &["B"]).len(), 1);
+  assert_eq!(graph.write_fetch_new_edges_by_kind("", "U1", &["B"]).len(), 0);
 
-  let res : Vec<(String, String, Weight)> = graph.read_graph("", "U1", "B2", false, 0, 10000);
+  graph.write_clear_seen("U1");
 
-  for x in res.iter() {
-    println!("{} -> {}: {}", x.0, x.1, x.2);
-  }
+  assert_eq!(graph.read_is_seen("U1", &["B1"]), vec![("B1".to_string(), false)]);
+  assert!(graph.read_new_edges_filter("U1").is_empty());
+  assert_eq!(graph.write_fetch_new_edges_by_kind("", "U1", &["B"]).len(), 1);
+}
 
-  assert_eq!(res.len(), 0);
+#[test]
+fn is_seen_unknown_nodes() {
+  let graph = AugMultiGraph::new();
+
+  assert_eq!(graph.read_is_seen("U1", &["B1"]), vec![("B1".to_string(), false)]);
 }
 
 #[test]
-fn new_edges_fetch() {
+fn seen_filter_stats() {
   let mut graph = AugMultiGraph::new();
 
-  graph.write_put_edge("", "U1", "U2", 1.0);
+  graph.write_put_edge("", "U1", "B1", 1.0);
+  graph.write_put_edge("", "U2", "B1", 1.0);
 
-  assert_eq!(graph.write_fetch_new_edges("U1", "B").len(), 0);
+  let (stats, total) = graph.read_seen_filter_stats();
 
-  graph.write_put_edge("", "U1", "B3", 2.0);
-  graph.write_put_edge("", "U2", "B4", 3.0);
+  assert!(stats.is_empty());
+  assert_eq!(total, (0, 0, 0.0, 0.0, 0.0));
 
-  let beacons = graph.write_fetch_new_edges("U1", "B");
+  graph.write_mark_seen("U2", &["B1"]);
+  graph.write_mark_seen("U1", &["B1", "U2"]);
 
-  assert_eq!(beacons.len(), 2);
-  assert_eq!(beacons[0].0, "B3");
-  assert_eq!(beacons[1].0, "B4");
+  let (stats, total) = graph.read_seen_filter_stats();
 
-  assert_eq!(graph.write_fetch_new_edges("U1", "B").len(), 0);
+  assert_eq!(stats.len(), 2);
+  assert_eq!(stats[0].0, "U1");
+  assert_eq!(stats[0].2, 2);
+  assert_eq!(stats[1].0, "U2");
+  assert_eq!(stats[1].2, 1);
+  assert!(stats[0].4 > stats[1].4);
+
+  assert_eq!(total.0, 2);
+  assert_eq!(total.1, stats[0].3 + stats[1].3);
+  assert!((total.2 - (stats[0].4 + stats[1].4) / 2.0).abs() < 1e-9);
+  assert_eq!(total.4, f64::max(stats[0].5, stats[1].5));
 }
 
 #[test]
@@ -2039,7 +3468,7 @@ fn new_edges_filter() {
   graph.write_put_edge("", "U2", "B4", 3.0);
 
   let filter = graph.read_new_edges_filter("U1");
-  assert_eq!(filter.len(), 32);
+  assert_eq!(filter.len(), SEEN_FILTER_BLOOM_HEADER_SIZE + 32);
 
   let beacons = graph.write_fetch_new_edges("U1", "B");
 